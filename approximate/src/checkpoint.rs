@@ -0,0 +1,409 @@
+// SPDX-FileCopyrightText: Alois Wohlschlager <wohlschlager@math.lmu.de>
+// SPDX-License-Identifier: EUPL-1.2
+
+//! Checkpoints for long computations. The results for the masks are appended to the checkpoint file
+//! in the order in which they are printed, each block ending with a line `done <mask>`, so that a
+//! block cut off by an interruption is recognized and dropped when resuming. Optionally, a snapshot
+//! of the multiplication cache is kept next to it, which is replaced regularly.
+
+use crate::{
+    products::Parameters, shard::Shard, BasisElement, Cache, Coefficient, Exponent, SortedElement,
+    ZMask,
+};
+use std::{
+    fs::{File, OpenOptions},
+    io::{BufWriter, ErrorKind, Write},
+    time::{Duration, Instant},
+};
+
+// the minimal time between two snapshots of the cache
+const CACHE_INTERVAL: Duration = Duration::from_secs(15 * 60);
+
+/// the rational elements u^{d1}c_{…}^* computed for one mask, by d1
+pub type Results<M, C> = Vec<(u16, SortedElement<M, C>)>;
+
+// the results for several masks, in descending order of the masks
+type Blocks<M, C> = Vec<(u128, Results<M, C>)>;
+
+pub struct Checkpoint {
+    // the parameters of the computation, at the beginning of all files
+    header: String,
+    writer: BufWriter<File>,
+    // the path of the snapshot of the cache and the time it was last written, if enabled
+    cache: Option<(String, Instant)>,
+}
+
+impl Checkpoint {
+    /// Open the checkpoint file for the computation with the given parameters, creating it if
+    /// necessary, and return the results recorded in it in descending order of the masks. If a
+    /// cache is given, it is filled from the snapshot, and snapshots are written from now on.
+    pub fn open<M: ZMask, C: Coefficient>(
+        path: &str,
+        parameters: &Parameters,
+        shard: Shard,
+        cache: Option<&Cache<M, C>>,
+    ) -> (Checkpoint, Blocks<M, C>) {
+        let Parameters {
+            n,
+            t,
+            connective,
+            ref truncation,
+            checksum,
+        } = *parameters;
+        // no header is a prefix of another one
+        let header = format!(
+            "# checkpoint of `approximate`\nn {n}\n{}{}{}order {}\nformula tables \
+             {checksum:016x}\n",
+            if connective { "connective\n" } else { "" },
+            if truncation.generators.is_empty() {
+                String::new()
+            } else {
+                format!("ideal {truncation}\n")
+            },
+            if shard == Shard::ALL {
+                String::new()
+            } else {
+                format!("shard {shard}\n")
+            },
+            truncation.order,
+        );
+        let (results, length) = match std::fs::read_to_string(path) {
+            Ok(s) => {
+                assert!(
+                    s.starts_with(&header),
+                    "the checkpoint {path} belongs to a different computation",
+                );
+                parse_results(&s[header.len()..], n, t, shard, header.len())
+            }
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                std::fs::write(path, &header)
+                    .unwrap_or_else(|e| panic!("cannot write {path}: {e}"));
+                (Vec::new(), header.len())
+            }
+            Err(e) => panic!("cannot read {path}: {e}"),
+        };
+        let file = OpenOptions::new()
+            .append(true)
+            .open(path)
+            .unwrap_or_else(|e| panic!("cannot write {path}: {e}"));
+        // drop an incomplete block at the end
+        file.set_len(u64::try_from(length).unwrap())
+            .unwrap_or_else(|e| panic!("cannot write {path}: {e}"));
+
+        let cache = cache.map(|cache| {
+            let cache_path = format!("{path}.cache");
+            match std::fs::read_to_string(&cache_path) {
+                Ok(s) => {
+                    assert!(
+                        s.starts_with(&header),
+                        "the cache snapshot {cache_path} belongs to a different computation",
+                    );
+                    parse_cache(&s[header.len()..], t, cache)
+                        .unwrap_or_else(|e| panic!("invalid cache snapshot {cache_path}: {e}"));
+                }
+                Err(e) if e.kind() == ErrorKind::NotFound => {}
+                Err(e) => panic!("cannot read {cache_path}: {e}"),
+            }
+            (cache_path, Instant::now())
+        });
+        (
+            Checkpoint {
+                header,
+                writer: BufWriter::new(file),
+                cache,
+            },
+            results,
+        )
+    }
+
+    pub fn record<M: ZMask, C: Coefficient>(&mut self, mask: u128, results: &Results<M, C>) {
+        let mut s = format!("mask {mask}\n");
+        for (d1, x) in results {
+            s += &format!(
+                "element {d1}{}\n",
+                format_terms(x.iter().map(|(m, c)| (m, c)))
+            );
+        }
+        s += &format!("done {mask}\n");
+        self.writer
+            .write_all(s.as_bytes())
+            .and_then(|()| self.writer.flush())
+            .unwrap_or_else(|e| panic!("cannot write checkpoint: {e}"));
+    }
+
+    /// Replace the snapshot of the cache if enough time has passed since the last one. The
+    /// snapshot is written to a temporary file first, so that it is never incomplete.
+    pub fn save_cache<M: ZMask, C: Coefficient>(&mut self, cache: &Cache<M, C>) {
+        let Some((path, last)) = &mut self.cache else {
+            return;
+        };
+        if last.elapsed() < CACHE_INTERVAL {
+            return;
+        }
+        let temporary = format!("{path}.tmp");
+        let mut writer = BufWriter::new(
+            File::create(&temporary).unwrap_or_else(|e| panic!("cannot write {temporary}: {e}")),
+        );
+        write!(writer, "{}", self.header)
+            .unwrap_or_else(|e| panic!("cannot write {temporary}: {e}"));
+        for shard in &cache.shards {
+            for ((m, k), entry) in &shard.lock().unwrap().entries {
+                writeln!(
+                    writer,
+                    "entry {k}{}{}",
+                    format_terms(std::iter::once((m, &C::ONE))),
+                    format_terms(entry.value.iter()),
+                )
+                .unwrap_or_else(|e| panic!("cannot write {temporary}: {e}"));
+            }
+        }
+        writer
+            .into_inner()
+            .map_err(|e| e.into_error())
+            .and_then(|file| file.sync_all())
+            .and_then(|()| std::fs::rename(&temporary, &*path))
+            .unwrap_or_else(|e| panic!("cannot write {path}: {e}"));
+        *last = Instant::now();
+    }
+}
+
+// the complete blocks of results following the header, and the length of the file up to the end
+// of the last of them
+fn parse_results<M: ZMask, C: Coefficient>(
+    s: &str,
+    n: u8,
+    t: u8,
+    shard: Shard,
+    header_length: usize,
+) -> (Blocks<M, C>, usize) {
+    let mut results = Vec::new();
+    let mut block = None;
+    let mut next = shard.previous(1 << (n - 1));
+    let mut offset = header_length;
+    let mut length = header_length;
+    // anything not fitting the expected structure can only come from an interrupted write, and is
+    // dropped together with the rest of the file
+    for line in s.split_inclusive('\n') {
+        offset += line.len();
+        let Some(line) = line.strip_suffix('\n') else {
+            break;
+        };
+        let tokens = line.split(' ').collect::<Vec<_>>();
+        match (tokens.as_slice(), &mut block) {
+            (["mask", mask], None) if mask.parse().ok() == next => {
+                block = Some((next.unwrap(), Vec::new()));
+            }
+            (["element", d1, terms @ ..], Some((_, x))) => {
+                let (Ok(d1), Some(terms)) = (d1.parse(), parse_terms(terms, t)) else {
+                    break;
+                };
+                x.push((d1, terms));
+            }
+            (["done", mask], Some((expected, _))) if mask.parse().ok() == Some(*expected) => {
+                let (mask, x) = block.take().unwrap();
+                results.push((mask, x));
+                next = shard.previous(mask);
+                length = offset;
+            }
+            _ => break,
+        }
+    }
+    (results, length)
+}
+
+fn parse_cache<M: ZMask, C: Coefficient>(
+    s: &str,
+    t: u8,
+    cache: &Cache<M, C>,
+) -> Result<(), String> {
+    for (i, line) in s.lines().enumerate() {
+        let error = || format!("line {}: invalid entry", i + 1);
+        let tokens = line.split(' ').collect::<Vec<_>>();
+        let ["entry", k, terms @ ..] = tokens.as_slice() else {
+            return Err(error());
+        };
+        let k = k.parse().map_err(|_| error())?;
+        let terms = parse_terms::<M, C>(terms, t).ok_or_else(error)?;
+        let Some(((m, _), x)) = terms.split_first() else {
+            return Err(error());
+        };
+        cache.insert((*m, k), x.iter().cloned().collect());
+    }
+    Ok(())
+}
+
+// each term as ` c v1 v2 v3 v4 zs`, with the z's given as a bit mask
+fn format_terms<'a, M: ZMask + 'a, C: Coefficient + 'a>(
+    terms: impl Iterator<Item = (&'a BasisElement<M>, &'a C)>,
+) -> String {
+    terms
+        .map(|(m, c)| {
+            format!(
+                " {c} {} {} {} {} {}",
+                m.v1,
+                m.v2,
+                m.v3,
+                m.v4,
+                m.zs.to_u128()
+            )
+        })
+        .collect()
+}
+
+fn parse_terms<M: ZMask, C: Coefficient>(tokens: &[&str], t: u8) -> Option<SortedElement<M, C>> {
+    let terms = tokens.chunks_exact(6);
+    if !terms.remainder().is_empty() {
+        return None;
+    }
+    terms
+        .map(|term| {
+            let c = C::from_i128(term[0].parse().ok()?, t);
+            let v = |i: usize| term[i].parse::<Exponent>().ok();
+            let zs = M::from_u128(term[5].parse().ok()?)?;
+            Some((
+                BasisElement {
+                    v1: v(1)?,
+                    v2: v(2)?,
+                    v3: v(3)?,
+                    v4: v(4)?,
+                    zs,
+                },
+                c,
+            ))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{temporary_path, truncation::Truncation};
+
+    fn parameters(checksum: u64) -> Parameters {
+        Parameters {
+            n: 4,
+            t: 3,
+            connective: false,
+            truncation: Truncation::order(4),
+            checksum,
+        }
+    }
+
+    fn element(terms: &[(i32, [Exponent; 4], u32)]) -> SortedElement<u32, i32> {
+        terms
+            .iter()
+            .map(|&(c, [v1, v2, v3, v4], zs)| (BasisElement { v1, v2, v3, v4, zs }, c))
+            .collect()
+    }
+
+    fn blocks() -> Blocks<u32, i32> {
+        vec![
+            (
+                7,
+                vec![
+                    (0, element(&[(1, [0; 4], 0b1111)])),
+                    (
+                        1,
+                        element(&[(3, [1, 0, 0, 0], 0b111), (5, [0, 1, 0, 0], 0b1)]),
+                    ),
+                ],
+            ),
+            (
+                6,
+                vec![(0, element(&[])), (2, element(&[(7, [4, 0, 0, 0], 0)]))],
+            ),
+        ]
+    }
+
+    fn write(path: &str) {
+        let (mut checkpoint, results) =
+            Checkpoint::open::<u32, i32>(path, &parameters(1), Shard::ALL, None);
+        assert!(results.is_empty());
+        for (mask, results) in blocks() {
+            checkpoint.record(mask, &results);
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let path = temporary_path("round-trip.checkpoint");
+        write(&path);
+        let (_, results) = Checkpoint::open::<u32, i32>(&path, &parameters(1), Shard::ALL, None);
+        assert!(results == blocks());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn incomplete_block() {
+        let path = temporary_path("incomplete.checkpoint");
+        write(&path);
+        let complete = std::fs::read_to_string(&path).unwrap();
+        std::fs::write(&path, format!("{complete}mask 5\nelement 0 1 0 0")).unwrap();
+        let (_, results) = Checkpoint::open::<u32, i32>(&path, &parameters(1), Shard::ALL, None);
+        assert!(results == blocks());
+        // the incomplete block is removed from the file
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), complete);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn different_tables() {
+        let path = temporary_path("different-tables.checkpoint");
+        write(&path);
+        let result = std::panic::catch_unwind(|| {
+            Checkpoint::open::<u32, i32>(&path, &parameters(2), Shard::ALL, None)
+        });
+        std::fs::remove_file(&path).unwrap();
+        let message = result.err().unwrap().downcast::<String>().unwrap();
+        assert!(message.contains("belongs to a different computation"));
+    }
+
+    #[test]
+    fn corrupt_results() {
+        for s in [
+            "mask 7\nelement x\ndone 7\n",
+            "mask 7\nelement 0 1 0 0 0\ndone 7\n",
+            "mask 7\ndone 6\n",
+            "mask 6\ndone 6\n",
+            "mask 7\nelement 0 1 0 0 0 0 4294967296\ndone 7\n",
+            "mask 7\ndone 7",
+        ] {
+            let (results, length) = parse_results::<u32, i32>(s, 4, 3, Shard::ALL, 10);
+            assert!(results.is_empty(), "{s:?}");
+            assert_eq!(length, 10, "{s:?}");
+        }
+        // only the complete blocks before the corruption are kept
+        let s = "mask 7\ndone 7\nmask 6\nelement 1 2\ndone 6\n";
+        let (results, length) = parse_results::<u32, i32>(s, 4, 3, Shard::ALL, 0);
+        assert!(results == vec![(7, Vec::new())]);
+        assert_eq!(length, "mask 7\ndone 7\n".len());
+    }
+
+    #[test]
+    fn cache_round_trip() {
+        let m = element(&[(1, [1, 0, 0, 0], 0b101)])[0].0;
+        let value = element(&[(3, [1, 0, 0, 0], 0b111), (6, [2, 0, 0, 0], 0b1)]);
+        let s = format!(
+            "entry 2{}{}\n",
+            format_terms(std::iter::once((&m, &1))),
+            format_terms(value.iter().map(|(m, c)| (m, c))),
+        );
+        let cache = Cache::new();
+        parse_cache::<u32, i32>(&s, 3, &cache).unwrap();
+        let mut stored = cache.get(&(m, 2)).unwrap().into_iter().collect::<Vec<_>>();
+        stored.sort();
+        assert!(stored == value);
+
+        for s in [
+            "entry 2\n",
+            "entry x 1 0 0 0 0 5\n",
+            "entry 2 1 0 0 0 0\n",
+            "mask 7\n",
+        ] {
+            assert!(
+                parse_cache::<u32, i32>(s, 3, &Cache::new()).is_err(),
+                "{s:?}"
+            );
+        }
+    }
+}
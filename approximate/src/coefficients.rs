@@ -0,0 +1,231 @@
+// SPDX-FileCopyrightText: Alois Wohlschlager <wohlschlager@math.lmu.de>
+// SPDX-License-Identifier: EUPL-1.2
+
+//! The coefficient rings other than the integers modulo 2^t. With the integers, the computation
+//! shows whether the reduction modulo 2^t ever hides information, while F_2 gives quick previews
+//! of the Chow ring modulo 2, and Z_(2) keeps the 2-adic valuations of the coefficients. The exact
+//! integers are the ones of `generate-exact`.
+
+use crate::Coefficient;
+use std::fmt::{Display, Formatter};
+
+pub use generate_exact::integer::Integer;
+
+/// The field with two elements. The exponent t is ignored, so it is best used with t = 1.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct F2(pub bool);
+
+impl Coefficient for F2 {
+    const ZERO: Self = F2(false);
+    const ONE: Self = F2(true);
+
+    fn from_i128(c: i128, _: u8) -> Self {
+        F2(c % 2 != 0)
+    }
+
+    fn add_product(&mut self, a: &Self, b: &Self, _: u8) {
+        self.0 ^= a.0 & b.0;
+    }
+
+    fn neg(&self, _: u8) -> Self {
+        *self
+    }
+
+    fn residue(&self, _: u8) -> u64 {
+        self.0.into()
+    }
+}
+
+impl Display for F2 {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", u8::from(self.0))
+    }
+}
+
+impl Coefficient for Integer {
+    const ZERO: Self = Integer::Small(0);
+    const ONE: Self = Integer::Small(1);
+
+    fn from_i128(c: i128, _: u8) -> Self {
+        integer(c)
+    }
+
+    fn add_product(&mut self, a: &Self, b: &Self, _: u8) {
+        *self = &*self + &(a * b);
+    }
+
+    fn neg(&self, _: u8) -> Self {
+        -self
+    }
+
+    fn residue(&self, t: u8) -> u64 {
+        match self.div_rem(&power_of_two(t.into())).1 {
+            Integer::Small(r) => u64::try_from(i128::from(r).rem_euclid(1 << t)).unwrap(),
+            Integer::Large(..) => unreachable!("remainder too large"),
+        }
+    }
+}
+
+/// The localization Z_(2), whose elements are written as 2^v*u with a unit u. Since the relations
+/// have integer coefficients and nothing is ever divided, u is always an odd integer. The
+/// valuations show directly how far the coefficients are divisible by 2.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Local {
+    valuation: u32,
+    // zero for the zero element, whose valuation is stored as 0
+    unit: Integer,
+}
+
+impl Local {
+    pub fn new(x: Integer) -> Self {
+        let two = Integer::from(2);
+        let mut result = Local {
+            valuation: 0,
+            unit: x,
+        };
+        while !result.unit.is_zero() {
+            let (q, r) = result.unit.div_rem(&two);
+            if !r.is_zero() {
+                break;
+            }
+            result.unit = q;
+            result.valuation += 1;
+        }
+        result
+    }
+
+    /// the valuation v, or `None` for zero
+    pub fn valuation(&self) -> Option<u32> {
+        (!self.unit.is_zero()).then_some(self.valuation)
+    }
+
+    /// the odd factor u, or zero for zero
+    pub fn unit(&self) -> &Integer {
+        &self.unit
+    }
+
+    pub fn value(&self) -> Integer {
+        &self.unit * &power_of_two(self.valuation)
+    }
+}
+
+impl Coefficient for Local {
+    const ZERO: Self = Local {
+        valuation: 0,
+        unit: Integer::Small(0),
+    };
+    const ONE: Self = Local {
+        valuation: 0,
+        unit: Integer::Small(1),
+    };
+
+    fn from_i128(c: i128, _: u8) -> Self {
+        Local::new(integer(c))
+    }
+
+    fn add_product(&mut self, a: &Self, b: &Self, _: u8) {
+        if a.unit.is_zero() || b.unit.is_zero() {
+            return;
+        }
+        // the product of two units is a unit again
+        let product = Local {
+            valuation: a.valuation + b.valuation,
+            unit: &a.unit * &b.unit,
+        };
+        if self.unit.is_zero() {
+            *self = product;
+            return;
+        }
+        let valuation = u32::min(self.valuation, product.valuation);
+        let sum = Local::new(
+            &(&self.unit * &power_of_two(self.valuation - valuation))
+                + &(&product.unit * &power_of_two(product.valuation - valuation)),
+        );
+        *self = Local {
+            valuation: if sum.unit.is_zero() {
+                0
+            } else {
+                sum.valuation + valuation
+            },
+            unit: sum.unit,
+        };
+    }
+
+    fn neg(&self, _: u8) -> Self {
+        Local {
+            valuation: self.valuation,
+            unit: -&self.unit,
+        }
+    }
+
+    fn residue(&self, t: u8) -> u64 {
+        if self.valuation >= t.into() {
+            return 0;
+        }
+        let result = u128::from(self.unit.residue(t)) << self.valuation;
+        u64::try_from(result % (1 << t)).unwrap()
+    }
+}
+
+// the value, so that the output has the same form as for the integers
+impl Display for Local {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.value())
+    }
+}
+
+fn integer(c: i128) -> Integer {
+    match i64::try_from(c) {
+        Ok(c) => Integer::from(c),
+        Err(_) => c.to_string().parse().unwrap(),
+    }
+}
+
+fn power_of_two(k: u32) -> Integer {
+    let mut result = Integer::one();
+    for _ in 0..k / 62 {
+        result = &result * &Integer::from(1 << 62);
+    }
+    &result * &Integer::from(1 << (k % 62))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn local_valuations() {
+        let x = Local::from_i128(-24, 4);
+        assert_eq!(x.valuation(), Some(3));
+        assert_eq!(*x.unit(), Integer::from(-3));
+        assert_eq!(x.value(), Integer::from(-24));
+        assert_eq!(Local::ZERO.valuation(), None);
+        assert_eq!(x.residue(4), 8);
+        assert_eq!(Local::from_i128(16, 4).residue(4), 0);
+    }
+
+    #[test]
+    fn local_arithmetic() {
+        // 12 + 2*10 = 32 = 2^5
+        let mut x = Local::from_i128(12, 8);
+        x.add_product(&Local::from_i128(2, 8), &Local::from_i128(10, 8), 8);
+        assert_eq!(x.valuation(), Some(5));
+        assert_eq!(x.value(), Integer::from(32));
+        // 32 - 4*8 = 0
+        x.add_product(&Local::from_i128(4, 8), &Local::from_i128(8, 8).neg(8), 8);
+        assert_eq!(x, Local::ZERO);
+        // large values do not lose any factors of 2
+        let mut y = Local::ZERO;
+        y.add_product(
+            &Local::from_i128(1 << 100, 8),
+            &Local::from_i128(3 << 40, 8),
+            8,
+        );
+        assert_eq!(y.valuation(), Some(140));
+        assert_eq!(y.residue(8), 0);
+        assert_eq!(
+            y.to_string(),
+            (&Integer::from(3) * &power_of_two(140)).to_string()
+        );
+    }
+}
@@ -0,0 +1,287 @@
+// SPDX-FileCopyrightText: Alois Wohlschlager <wohlschlager@math.lmu.de>
+// SPDX-License-Identifier: EUPL-1.2
+
+//! Comparison with the exact results, as printed by the last Singular stage or by `generate-exact
+//! --native` in the form `u^d1*cs(i)*…=<polynomial in the z(i) and v1,…,v4>`. The exact elements
+//! are reduced modulo 2^t and modulo the truncation ideal in the v's, so that they can be compared
+//! term by term with the approximate ones.
+
+use crate::{
+    output::Key, truncation::Truncation, BasisElement, Coefficient, Element, Exponent, ZMask,
+};
+use generate_exact::integer::inverse;
+use std::{collections::BTreeMap, iter::Peekable, str::Chars};
+
+/// The exact elements u^{d1}c_{cs}^* in the range covered by the truncation. Since c_i^* = -cs(i),
+/// the sign is changed for an odd number of Chern classes.
+pub fn parse<M: ZMask, C: Coefficient>(
+    s: &str,
+    n: u8,
+    t: u8,
+    truncation: &Truncation,
+) -> Result<BTreeMap<Key, Element<M, C>>, String> {
+    let d = u16::from(n) * u16::from(n + 1) / 2;
+    let mut result = BTreeMap::new();
+    for (i, line) in s.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let error = |e: &str| format!("line {}: {e}", i + 1);
+        let (lhs, rhs) = line
+            .split_once('=')
+            .ok_or_else(|| error(&format!("invalid line {line:?}")))?;
+        let (d1, cs) = parse_lhs(lhs, n).ok_or_else(|| error(&format!("invalid line {line:?}")))?;
+        let degree = d1 + cs.iter().copied().map(u16::from).sum::<u16>();
+        if degree > d || degree < d.saturating_sub(truncation.exact_order().into()) {
+            continue;
+        }
+        let x = parse_polynomial::<M>(rhs, n).map_err(|e| error(&e))?;
+        let sign = if cs.len() % 2 == 0 { 1 } else { u64::MAX };
+        let x = x
+            .into_iter()
+            .filter(|(m, _)| !truncation.drops(m))
+            .map(|(m, c)| (m, c.wrapping_mul(sign) & ((1 << t) - 1)))
+            .filter(|(_, c)| *c != 0)
+            .map(|(m, c)| (m, C::from_i128(c.into(), t)))
+            .collect();
+        if result.insert((d1, cs), x).is_some() {
+            return Err(error("duplicate rational element"));
+        }
+    }
+    Ok(result)
+}
+
+/// the terms of x and y with different coefficients, as (basis element, coefficient in x,
+/// coefficient in y)
+pub fn differences<M: ZMask, C: Coefficient>(
+    x: &Element<M, C>,
+    y: &Element<M, C>,
+) -> Vec<(BasisElement<M>, C, C)> {
+    let mut result = x
+        .iter()
+        .map(|(m, c)| (*m, c.clone(), y.get(m).cloned().unwrap_or(C::ZERO)))
+        .chain(
+            y.iter()
+                .filter(|(m, _)| !x.contains_key(m))
+                .map(|(m, c)| (*m, C::ZERO, c.clone())),
+        )
+        .filter(|(_, c1, c2)| c1 != c2)
+        .collect::<Vec<_>>();
+    result.sort_unstable_by_key(|(m, _, _)| *m);
+    result
+}
+
+// `u^d1*cs(i)*…`, with the Chern classes in increasing order
+fn parse_lhs(lhs: &str, n: u8) -> Option<Key> {
+    let mut factors = lhs.trim().split('*');
+    let d1 = factors.next()?.strip_prefix("u^")?.parse().ok()?;
+    let cs = factors
+        .map(|factor| factor.strip_prefix("cs(")?.strip_suffix(')')?.parse().ok())
+        .collect::<Option<Vec<u8>>>()?;
+    (cs.iter().all(|i| (2..=n).contains(i)) && cs.is_sorted_by(|i, j| i < j)).then_some((d1, cs))
+}
+
+// polynomials with coefficients modulo 2^64, which is enough to determine them modulo 2^t
+type Polynomial<M> = BTreeMap<BasisElement<M>, u64>;
+
+// Parse a polynomial written in Singular syntax. Only odd denominators are allowed, and since the
+// exact elements are reduced, products of z's with a common index are rejected.
+fn parse_polynomial<M: ZMask>(s: &str, n: u8) -> Result<Polynomial<M>, String> {
+    let chars = s.chars().filter(|c| !c.is_whitespace()).collect::<String>();
+    let mut parser = Parser {
+        chars: chars.chars().peekable(),
+        n,
+    };
+    let result = parser.sum()?;
+    match parser.chars.next() {
+        None => Ok(result),
+        Some(c) => Err(format!("unexpected character {c:?}")),
+    }
+}
+
+fn one<M: ZMask>() -> BasisElement<M> {
+    BasisElement {
+        v1: 0,
+        v2: 0,
+        v3: 0,
+        v4: 0,
+        zs: M::ZERO,
+    }
+}
+
+fn constant<M: ZMask>(c: u64) -> Polynomial<M> {
+    BTreeMap::from_iter((c != 0).then_some((one(), c)))
+}
+
+fn scale<M: ZMask>(x: Polynomial<M>, c: u64) -> Polynomial<M> {
+    x.into_iter()
+        .map(|(m, c2)| (m, c.wrapping_mul(c2)))
+        .filter(|(_, c)| *c != 0)
+        .collect()
+}
+
+fn add<M: ZMask>(x: &mut Polynomial<M>, y: Polynomial<M>) {
+    for (m, c) in y {
+        let entry = x.entry(m).or_insert(0);
+        *entry = entry.wrapping_add(c);
+        if *entry == 0 {
+            x.remove(&m);
+        }
+    }
+}
+
+fn mul<M: ZMask>(x: &Polynomial<M>, y: &Polynomial<M>) -> Result<Polynomial<M>, String> {
+    let mut result = BTreeMap::new();
+    for (m1, c1) in x {
+        for (m2, c2) in y {
+            if m1.zs & m2.zs != M::ZERO {
+                return Err("unreduced product of z's".to_owned());
+            }
+            let e = |e1: Exponent, e2: Exponent| e1.checked_add(e2).ok_or("exponent too large");
+            let m = BasisElement {
+                v1: e(m1.v1, m2.v1)?,
+                v2: e(m1.v2, m2.v2)?,
+                v3: e(m1.v3, m2.v3)?,
+                v4: e(m1.v4, m2.v4)?,
+                zs: m1.zs | m2.zs,
+            };
+            add(&mut result, BTreeMap::from([(m, c1.wrapping_mul(*c2))]));
+        }
+    }
+    Ok(result)
+}
+
+struct Parser<'a> {
+    chars: Peekable<Chars<'a>>,
+    n: u8,
+}
+
+impl Parser<'_> {
+    fn sum<M: ZMask>(&mut self) -> Result<Polynomial<M>, String> {
+        let mut result = BTreeMap::new();
+        let mut negative = self.chars.next_if_eq(&'-').is_some();
+        if !negative {
+            self.chars.next_if_eq(&'+');
+        }
+        loop {
+            let summand = self.product()?;
+            add(
+                &mut result,
+                if negative {
+                    scale(summand, u64::MAX)
+                } else {
+                    summand
+                },
+            );
+            negative = match self.chars.peek() {
+                Some('+') => false,
+                Some('-') => true,
+                _ => return Ok(result),
+            };
+            self.chars.next();
+        }
+    }
+
+    fn product<M: ZMask>(&mut self) -> Result<Polynomial<M>, String> {
+        let mut result = self.power()?;
+        loop {
+            match self.chars.peek() {
+                Some('*') => {
+                    self.chars.next();
+                    result = mul(&result, &self.power()?)?;
+                }
+                Some('/') => {
+                    self.chars.next();
+                    let divisor = self.power::<M>()?.into_iter().collect::<Vec<_>>();
+                    let divisor = match divisor.as_slice() {
+                        [(m, c)] if *m == one() => *c,
+                        _ => return Err("division by a non-constant".to_owned()),
+                    };
+                    if divisor % 2 == 0 {
+                        return Err("division by an even number".to_owned());
+                    }
+                    result = scale(result, inverse(divisor));
+                }
+                _ => return Ok(result),
+            }
+        }
+    }
+
+    fn power<M: ZMask>(&mut self) -> Result<Polynomial<M>, String> {
+        let base = self.atom()?;
+        if self.chars.peek() == Some(&'^') {
+            self.chars.next();
+            let exponent = self
+                .digits()?
+                .parse::<u32>()
+                .map_err(|_| "exponent too large")?;
+            (0..exponent).try_fold(constant(1), |accum, _| mul(&accum, &base))
+        } else {
+            Ok(base)
+        }
+    }
+
+    fn atom<M: ZMask>(&mut self) -> Result<Polynomial<M>, String> {
+        match self.chars.peek() {
+            Some('(') => {
+                self.chars.next();
+                let result = self.sum()?;
+                match self.chars.next() {
+                    Some(')') => Ok(result),
+                    c => Err(format!("expected ')', found {c:?}")),
+                }
+            }
+            // the value modulo 2^64
+            Some(c) if c.is_ascii_digit() => Ok(constant(
+                self.digits()?.bytes().fold(0_u64, |accum, digit| {
+                    accum.wrapping_mul(10).wrapping_add(u64::from(digit - b'0'))
+                }),
+            )),
+            Some(c) if c.is_ascii_alphabetic() => {
+                let mut name = String::new();
+                while let Some(c) = self.chars.peek().filter(|c| c.is_ascii_alphanumeric()) {
+                    name.push(*c);
+                    self.chars.next();
+                }
+                let mut m = one::<M>();
+                match name.as_str() {
+                    "v1" => m.v1 = 1,
+                    "v2" => m.v2 = 1,
+                    "v3" => m.v3 = 1,
+                    "v4" => m.v4 = 1,
+                    "z" => {
+                        if self.chars.next() != Some('(') {
+                            return Err("missing index of z".to_owned());
+                        }
+                        let i = self.digits()?;
+                        if self.chars.next() != Some(')') {
+                            return Err(format!("unterminated index in variable z({i})"));
+                        }
+                        m.zs = i
+                            .parse()
+                            .ok()
+                            .filter(|i| (1..=self.n).contains(i))
+                            .map(M::z)
+                            .ok_or_else(|| format!("invalid variable z({i})"))?;
+                    }
+                    _ => return Err(format!("unknown variable {name}")),
+                }
+                Ok(BTreeMap::from([(m, 1)]))
+            }
+            c => Err(format!("unexpected character {c:?}")),
+        }
+    }
+
+    fn digits(&mut self) -> Result<String, String> {
+        let mut result = String::new();
+        while let Some(c) = self.chars.peek().filter(|c| c.is_ascii_digit()) {
+            result.push(*c);
+            self.chars.next();
+        }
+        if result.is_empty() {
+            Err("expected a number".to_owned())
+        } else {
+            Ok(result)
+        }
+    }
+}
@@ -0,0 +1,591 @@
+// SPDX-FileCopyrightText: Alois Wohlschlager <wohlschlager@math.lmu.de>
+// SPDX-License-Identifier: EUPL-1.2
+
+//! The Brown-Peterson cohomology of the maximal orthogonal Grassmannian OGr(n), truncated modulo a
+//! monomial ideal in the v's ([`truncation::Truncation`]), usually the one of all monomials above
+//! some degree, and usually with coefficients modulo 2^t.
+//!
+//! The ring is described by the formulas for u, the c_i^* and the squares z_k^2 from a
+//! [`table::Table`], collected in [`Relations`]. Its elements are linear combinations of the
+//! [`BasisElement`]s v_1^e1…v_4^e4z_{…} with coefficients in a ring implementing [`Coefficient`],
+//! which are multiplied by [`multiply_element_element`] with the help of a [`Cache`] of the
+//! products already known. The rational elements u^{d1}c_{…}^* are computed by
+//! [`rational_element`].
+//!
+//! For most purposes, it is more convenient to use [`ring::Ring`], which owns the relations and
+//! the cache, and whose elements support the usual arithmetic operators.
+
+use rustc_hash::FxHasher;
+use std::{
+    collections::HashMap,
+    fmt::{Display, Formatter},
+    hash::{BuildHasher, BuildHasherDefault, Hash},
+    ops::{BitAnd, BitOr, Not},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+};
+
+pub mod checkpoint;
+pub mod coefficients;
+pub mod compare;
+pub mod masks;
+pub mod matrices;
+pub mod output;
+pub mod products;
+pub mod progress;
+pub mod ring;
+pub mod shard;
+pub mod table;
+pub mod torsion;
+pub mod truncation;
+
+use table::Table;
+use truncation::Truncation;
+
+/// the formula tables up to degree 4 in the v's, generated by `generate-exact --table 4`
+pub const DEFAULT_TABLE: &str = include_str!("../tables/order4.txt");
+
+pub type Exponent = u8;
+
+/// The integer types used as bit masks of the z's. The width needs to be at least n.
+pub trait ZMask:
+    Copy + Ord + Hash + Send + Sync + BitAnd<Output = Self> + BitOr<Output = Self> + Not<Output = Self>
+{
+    const BITS: u32;
+    const ZERO: Self;
+
+    /// the mask of z_i
+    fn z(i: u8) -> Self;
+    /// the mask of z_1, …, z_n
+    fn first(n: u8) -> Self;
+    fn ilog2(self) -> u32;
+    fn to_u128(self) -> u128;
+    fn from_u128(x: u128) -> Option<Self>;
+}
+
+macro_rules! impl_z_mask {
+    ($($t:ty),*) => {$(
+        impl ZMask for $t {
+            const BITS: u32 = <$t>::BITS;
+            const ZERO: Self = 0;
+
+            fn z(i: u8) -> Self {
+                1 << (i - 1)
+            }
+
+            fn first(n: u8) -> Self {
+                <$t>::MAX.checked_shr(<$t>::BITS - u32::from(n)).unwrap_or(0)
+            }
+
+            fn ilog2(self) -> u32 {
+                <$t>::ilog2(self)
+            }
+
+            fn to_u128(self) -> u128 {
+                self.into()
+            }
+
+            fn from_u128(x: u128) -> Option<Self> {
+                x.try_into().ok()
+            }
+        }
+    )*};
+}
+
+impl_z_mask!(u32, u64, u128);
+
+/// The coefficient rings. All operations take the exponent t, but only the integers modulo 2^t
+/// depend on it, for which the primitive integer types are used. Products of two reduced
+/// coefficients need to fit, so the width needs to be larger than 2t+1. The other rings are
+/// found in [`coefficients`].
+pub trait Coefficient: Clone + Eq + Display + Send + Sync {
+    const ZERO: Self;
+    const ONE: Self;
+
+    /// the image of the integer c
+    fn from_i128(c: i128, t: u8) -> Self;
+    /// self + a*b
+    fn add_product(&mut self, a: &Self, b: &Self, t: u8);
+    fn neg(&self, t: u8) -> Self;
+    /// the image in Z/2^t, for the torsion analysis
+    fn residue(&self, t: u8) -> u64;
+}
+
+macro_rules! impl_coefficient {
+    ($($t:ty),*) => {$(
+        impl Coefficient for $t {
+            const ZERO: Self = 0;
+            const ONE: Self = 1;
+
+            fn from_i128(c: i128, t: u8) -> Self {
+                Self::try_from(c.rem_euclid(1 << t)).unwrap()
+            }
+
+            fn add_product(&mut self, a: &Self, b: &Self, t: u8) {
+                *self = (*self + a * b) & ((1 << t) - 1);
+            }
+
+            fn neg(&self, t: u8) -> Self {
+                -self & ((1 << t) - 1)
+            }
+
+            fn residue(&self, _: u8) -> u64 {
+                u64::try_from(*self).unwrap()
+            }
+        }
+    )*};
+}
+
+impl_coefficient!(i32, i64, i128);
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BasisElement<M> {
+    pub v1: Exponent,
+    pub v2: Exponent,
+    pub v3: Exponent,
+    pub v4: Exponent,
+    /// the mask of the z's
+    pub zs: M,
+}
+
+impl<M: ZMask> BasisElement<M> {
+    /// the indices of the z's
+    pub fn indices(&self) -> impl Iterator<Item = u32> + '_ {
+        (1..=M::BITS).filter(|i| self.zs & M::z(u8::try_from(*i).unwrap()) != M::ZERO)
+    }
+
+    /// the exponents of the v's followed by the indices of the z's, separated by spaces
+    pub fn format(&self) -> String {
+        [self.v1, self.v2, self.v3, self.v4]
+            .map(u32::from)
+            .into_iter()
+            .chain(self.indices())
+            .map(|x| x.to_string())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+impl<M: ZMask> Display for BasisElement<M> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (i, e) in [self.v1, self.v2, self.v3, self.v4].into_iter().enumerate() {
+            match e {
+                0 => {}
+                1 => write!(f, "v_{}", i + 1)?,
+                e => write!(f, "v_{}^{e}", i + 1)?,
+            }
+        }
+        write!(
+            f,
+            "z_{{{}}}",
+            self.indices()
+                .map(|i| i.to_string())
+                .collect::<Vec<_>>()
+                .join(",")
+        )
+    }
+}
+
+pub type Element<M, C> = HashMap<BasisElement<M>, C, BuildHasherDefault<FxHasher>>;
+
+/// the terms of an element in ascending order, for deterministic output
+pub type SortedElement<M, C> = Vec<(BasisElement<M>, C)>;
+
+type Key<M> = (BasisElement<M>, u8);
+
+struct Shard<M, C> {
+    entries: HashMap<Key<M>, Entry<M, C>>,
+    // the estimated memory used by the entries
+    bytes: usize,
+}
+
+struct Entry<M, C> {
+    value: Element<M, C>,
+    // whether the entry was inserted or used since the last eviction in its shard
+    used: bool,
+}
+
+/// The products m*z_k computed so far, shared between all threads. It is split into shards with
+/// separate locks to avoid contention.
+///
+/// Optionally, the memory is bounded by a budget, which is split evenly between the shards.
+/// Whenever a shard exceeds its part, the entries neither inserted nor used since the last
+/// eviction in it are dropped, and if this is not enough, arbitrary further ones. Dropped products
+/// are simply computed again when needed.
+pub struct Cache<M, C> {
+    shards: Vec<Mutex<Shard<M, C>>>,
+    // the budget of each shard in bytes, if any
+    budget: Option<usize>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    evictions: AtomicU64,
+}
+
+/// the statistics of a [`Cache`]
+#[derive(Clone, Copy, Debug)]
+pub struct Statistics {
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+    pub entries: usize,
+    /// the estimated memory used by the entries
+    pub bytes: usize,
+}
+
+impl<M: ZMask, C: Coefficient> Cache<M, C> {
+    const SHARDS: u64 = 256;
+
+    /// the cache keeping all products
+    pub fn new() -> Self {
+        Cache {
+            shards: (0..Self::SHARDS)
+                .map(|_| {
+                    Mutex::new(Shard {
+                        entries: HashMap::new(),
+                        bytes: 0,
+                    })
+                })
+                .collect(),
+            budget: None,
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            evictions: AtomicU64::new(0),
+        }
+    }
+
+    /// the cache keeping products of an estimated size of at most `budget` bytes
+    pub fn with_budget(budget: usize) -> Self {
+        Cache {
+            budget: Some(budget / usize::try_from(Self::SHARDS).unwrap()),
+            ..Self::new()
+        }
+    }
+
+    pub fn statistics(&self) -> Statistics {
+        let (entries, bytes) = self.shards.iter().fold((0, 0), |(entries, bytes), shard| {
+            let shard = shard.lock().unwrap();
+            (entries + shard.entries.len(), bytes + shard.bytes)
+        });
+        Statistics {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            evictions: self.evictions.load(Ordering::Relaxed),
+            entries,
+            bytes,
+        }
+    }
+
+    fn shard(&self, key: &Key<M>) -> &Mutex<Shard<M, C>> {
+        let hash = BuildHasherDefault::<FxHasher>::default().hash_one(key);
+        &self.shards[usize::try_from(hash % Self::SHARDS).unwrap()]
+    }
+
+    // the estimated memory used by an entry, ignoring any memory owned by the coefficients
+    fn size(value: &Element<M, C>) -> usize {
+        std::mem::size_of::<(Key<M>, Entry<M, C>)>()
+            + value.capacity() * std::mem::size_of::<(BasisElement<M>, C)>()
+    }
+
+    fn get(&self, key: &Key<M>) -> Option<Element<M, C>> {
+        let result = self
+            .shard(key)
+            .lock()
+            .unwrap()
+            .entries
+            .get_mut(key)
+            .map(|entry| {
+                entry.used = true;
+                entry.value.clone()
+            });
+        let counter = if result.is_some() {
+            &self.hits
+        } else {
+            &self.misses
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        result
+    }
+
+    fn insert(&self, key: Key<M>, value: Element<M, C>) {
+        let mut shard = self.shard(&key).lock().unwrap();
+        shard.bytes += Self::size(&value);
+        // new entries count as used, so that they survive the next eviction
+        let used = true;
+        if let Some(old) = shard.entries.insert(key, Entry { value, used }) {
+            shard.bytes -= Self::size(&old.value);
+        }
+        let Some(budget) = self.budget else {
+            return;
+        };
+        if shard.bytes > budget {
+            let mut bytes = 0;
+            let mut evictions = 0;
+            shard.entries.retain(|_, entry| {
+                let keep = std::mem::replace(&mut entry.used, false)
+                    && bytes + Self::size(&entry.value) <= budget;
+                if keep {
+                    bytes += Self::size(&entry.value);
+                } else {
+                    evictions += 1;
+                }
+                keep
+            });
+            shard.bytes = bytes;
+            self.evictions.fetch_add(evictions, Ordering::Relaxed);
+        }
+    }
+}
+
+impl<M: ZMask, C: Coefficient> Default for Cache<M, C> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Display for Statistics {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let lookups = self.hits + self.misses;
+        write!(
+            f,
+            "{} hits and {} misses ({:.1}% hits), {} evictions, {} entries of about {:.1} MiB",
+            self.hits,
+            self.misses,
+            if lookups == 0 {
+                0.0
+            } else {
+                100.0 * self.hits as f64 / lookups as f64
+            },
+            self.evictions,
+            self.entries,
+            self.bytes as f64 / f64::from(1 << 20),
+        )
+    }
+}
+
+/// the formulas for u, the c_i^* and the squares z_k^2, reduced modulo 2^t and the truncation
+pub struct Relations<M, C> {
+    u: Element<M, C>,
+    duals: HashMap<u8, Element<M, C>>,
+    squares: HashMap<u8, Element<M, C>>,
+}
+
+impl<M: ZMask, C: Coefficient> Relations<M, C> {
+    pub fn new(table: &Table, n: u8, t: u8, truncation: &Truncation) -> Self {
+        Relations {
+            u: element(table.u().cloned(), n, t, truncation),
+            duals: (1..=n)
+                .map(|i| (i, element(table.dual(i), n, t, truncation)))
+                .collect(),
+            squares: (1..=n)
+                .map(|k| (k, element(table.square(k), n, t, truncation)))
+                .collect(),
+        }
+    }
+}
+
+fn term<M: ZMask, C>(
+    c: C,
+    [v1, v2, v3, v4]: [Exponent; 4],
+    zs: &[u16],
+    n: u8,
+) -> Option<(BasisElement<M>, C)> {
+    zs.iter().all(|i| *i <= u16::from(n)).then(|| {
+        (
+            BasisElement {
+                v1,
+                v2,
+                v3,
+                v4,
+                zs: zs
+                    .iter()
+                    .map(|i| M::z(u8::try_from(*i).unwrap()))
+                    .fold(M::ZERO, BitOr::bitor),
+            },
+            c,
+        )
+    })
+}
+
+fn multiply_monomial_z<M: ZMask, C: Coefficient>(
+    m: BasisElement<M>,
+    k: u8,
+    t: u8,
+    truncation: &Truncation,
+    squares: &HashMap<u8, Element<M, C>>,
+    cache: &Cache<M, C>,
+) -> Element<M, C> {
+    // the lock is not held during the computation, which needs other entries; if several threads
+    // compute the same product, they get the same result anyway
+    if let Some(result) = cache.get(&(m, k)) {
+        return result;
+    }
+    let result = if m.zs & M::z(k) == M::ZERO {
+        Element::from_iter(std::iter::once((
+            BasisElement {
+                zs: m.zs | M::z(k),
+                ..m
+            },
+            C::ONE,
+        )))
+    } else {
+        multiply_element_monomial(
+            squares[&k].clone(),
+            BasisElement {
+                zs: m.zs & !M::z(k),
+                ..m
+            },
+            t,
+            truncation,
+            squares,
+            cache,
+        )
+    };
+    cache.insert((m, k), result.clone());
+    result
+}
+
+fn multiply_monomial_monomial<M: ZMask, C: Coefficient>(
+    m1: BasisElement<M>,
+    m2: BasisElement<M>,
+    t: u8,
+    truncation: &Truncation,
+    squares: &HashMap<u8, Element<M, C>>,
+    cache: &Cache<M, C>,
+) -> Element<M, C> {
+    if m2.zs == M::ZERO {
+        let m = BasisElement {
+            v1: m1.v1 + m2.v1,
+            v2: m1.v2 + m2.v2,
+            v3: m1.v3 + m2.v3,
+            v4: m1.v4 + m2.v4,
+            zs: m1.zs,
+        };
+        if truncation.drops(&m) {
+            Element::default()
+        } else {
+            Element::from_iter(std::iter::once((m, C::ONE)))
+        }
+    } else {
+        let k = u8::try_from(m2.zs.ilog2() + 1).unwrap();
+        multiply_element_monomial(
+            multiply_monomial_z(m1, k, t, truncation, squares, cache),
+            BasisElement {
+                zs: m2.zs & !M::z(k),
+                ..m2
+            },
+            t,
+            truncation,
+            squares,
+            cache,
+        )
+    }
+}
+
+fn multiply_element_monomial<M: ZMask, C: Coefficient>(
+    e: Element<M, C>,
+    m2: BasisElement<M>,
+    t: u8,
+    truncation: &Truncation,
+    squares: &HashMap<u8, Element<M, C>>,
+    cache: &Cache<M, C>,
+) -> Element<M, C> {
+    let mut result = Element::default();
+    for (m1, c1) in e {
+        for (m, c) in multiply_monomial_monomial(m1, m2, t, truncation, squares, cache) {
+            result.entry(m).or_insert(C::ZERO).add_product(&c1, &c, t);
+        }
+    }
+    result.retain(|_, c| *c != C::ZERO);
+    result
+}
+
+pub fn multiply_element_element<M: ZMask, C: Coefficient>(
+    e1: Element<M, C>,
+    e2: Element<M, C>,
+    t: u8,
+    truncation: &Truncation,
+    squares: &HashMap<u8, Element<M, C>>,
+    cache: &Cache<M, C>,
+) -> Element<M, C> {
+    let mut result = Element::default();
+    for (m2, c2) in e2 {
+        for (m, c) in multiply_element_monomial(e1.clone(), m2, t, truncation, squares, cache) {
+            result.entry(m).or_insert(C::ZERO).add_product(&c, &c2, t);
+        }
+    }
+    result.retain(|_, c| *c != C::ZERO);
+    result
+}
+
+// the element described by the given terms from the formula tables
+fn element<M: ZMask, C: Coefficient>(
+    terms: impl IntoIterator<Item = table::Term>,
+    n: u8,
+    t: u8,
+    truncation: &Truncation,
+) -> Element<M, C> {
+    terms
+        .into_iter()
+        .filter(|x| !truncation.contains(&x.e))
+        .flat_map(|x| term(C::from_i128(x.c, t), x.e, &x.zs, n))
+        .filter(|(m, c)| *c != C::ZERO && m.zs == m.zs & M::first(n))
+        .collect()
+}
+
+pub fn rational_element<M: ZMask, C: Coefficient>(
+    k: u16,
+    cs: &[u8],
+    n: u8,
+    t: u8,
+    truncation: &Truncation,
+    relations: &Relations<M, C>,
+    cache: &Cache<M, C>,
+) -> Element<M, C> {
+    std::iter::repeat_n(relations.u.clone(), k.into())
+        .chain(cs.iter().map(|i| relations.duals[i].clone()))
+        .rfold(
+            Element::from_iter(term(C::ONE, [0; 4], &[], n)),
+            |accum, e| multiply_element_element(accum, e, t, truncation, &relations.squares, cache),
+        )
+}
+
+pub fn torsion_exponent(n: u8) -> u8 {
+    if n == 0 {
+        return 0;
+    }
+    let n_ = u16::from(n);
+    let temp = n_ - u16::try_from((n_ * (n_ + 1) / 2 + 1).ilog2()).unwrap();
+    let e = u8::try_from(n.ilog2()).unwrap();
+    let b = n - (1 << e);
+    let exceptional = 2 * b + 3 <= e + torsion_exponent(b);
+    u8::try_from(temp).unwrap() + if exceptional { 1 } else { 0 }
+}
+
+/// the indices i of the c_i^* in the rational elements for the given mask
+pub fn chern_classes(mask: u128, n: u8) -> Vec<u8> {
+    (2..=n).filter(|i| mask & (1 << (i - 2)) != 0).collect()
+}
+
+/// all monomials in the v's of degree exactly w
+pub fn v_monomials(w: u16) -> Vec<[Exponent; 4]> {
+    let mut result = Vec::new();
+    for e4 in 0..=w / 15 {
+        for e3 in 0..=(w - 15 * e4) / 7 {
+            for e2 in 0..=(w - 15 * e4 - 7 * e3) / 3 {
+                let e1 = w - 15 * e4 - 7 * e3 - 3 * e2;
+                result.push([e1, e2, e3, e4].map(|e| Exponent::try_from(e).unwrap()));
+            }
+        }
+    }
+    result
+}
+
+// a fresh path in the temporary directory for the tests
+#[cfg(test)]
+fn temporary_path(name: &str) -> String {
+    let path = std::env::temp_dir().join(format!("approximate-test-{}-{name}", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    path.to_str().unwrap().to_owned()
+}
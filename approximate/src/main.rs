@@ -1,501 +1,755 @@
 // SPDX-FileCopyrightText: Alois Wohlschlager <wohlschlager@math.lmu.de>
 // SPDX-License-Identifier: EUPL-1.2
 
-use rustc_hash::FxHasher;
+use approximate::{
+    checkpoint::{Checkpoint, Results},
+    chern_classes,
+    coefficients::{Integer, Local, F2},
+    compare,
+    masks::Masks,
+    matrices::Matrices,
+    output, products,
+    progress::Progress,
+    ring::Ring,
+    shard::{self, Shard},
+    table::Table,
+    torsion, torsion_exponent,
+    truncation::Truncation,
+    v_monomials, BasisElement, Cache, Coefficient, Exponent, SortedElement, ZMask, DEFAULT_TABLE,
+};
 use std::{
-    collections::HashMap,
-    fmt::{Display, Formatter},
-    hash::BuildHasherDefault,
-    ops::BitOr,
+    collections::{BTreeMap, HashMap},
+    num::NonZero,
+    sync::mpsc,
 };
 
-type Exponent = u8;
-type Coefficient = i32;
-type ZMask = u32;
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Format {
+    // `u^{d1}c_{…}^*≡…`, readable by humans
+    Text,
+    // one JSON object per line, for processing by other programs
+    Jsonl,
+}
 
-#[derive(Clone, Copy, PartialEq, Eq, Hash)]
-struct BasisElement {
-    v1: Exponent,
-    v2: Exponent,
-    zs: ZMask,
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Coefficients {
+    // the integers modulo 2^t
+    Modular,
+    // the integers modulo 2, for previews of the Chow ring modulo 2
+    F2,
+    // the exact integers, to see whether the reduction modulo 2^t hides information
+    Integer,
+    // the 2-local integers Z_(2), keeping the 2-adic valuation of every coefficient
+    Local,
 }
 
-impl Display for BasisElement {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        match self.v1 {
-            0 => {}
-            1 => write!(f, "v_1")?,
-            e => write!(f, "v_1^{e}")?,
-        }
-        match self.v2 {
-            0 => {}
-            1 => write!(f, "v_2")?,
-            e => write!(f, "v_2^{e}")?,
+// the settings given on the command line
+struct Options {
+    table: Table,
+    // compute modulo all monomials in the v's of degree larger than the order, and the further
+    // generators of the ideal
+    truncation: Truncation,
+    // search for torsion in the module of irrational elements after the computation
+    torsion: bool,
+    // write certificates for the torsion found to this file
+    certificate: Option<String>,
+    // the number of threads computing the rational elements
+    threads: usize,
+    format: Format,
+    // read the rational elements from the output of an earlier run instead of computing them
+    input: Option<String>,
+    // record the progress in this file, and resume from it if it exists
+    checkpoint: Option<String>,
+    // also keep snapshots of the multiplication cache next to the checkpoint
+    checkpoint_cache: bool,
+    // compare the rational elements with the exact ones in this file instead of printing them
+    compare: Option<String>,
+    coefficients: Coefficients,
+    // work in the Chow ring, where all v's vanish, and report the candidate degrees in all degrees
+    // instead of printing the rational elements
+    chow: bool,
+    // work in connective K-theory, where all v's except v1 vanish
+    ck: bool,
+    // multiply by u using the precomputed sparse matrices of the multiplications by the z_k
+    matrices: bool,
+    // bound the estimated memory of the multiplication cache by this many bytes
+    cache_budget: Option<usize>,
+    // print the statistics of the multiplication cache to stderr after the computation
+    cache_statistics: bool,
+    // load the multiplication cache from this file, and write it back after the computation
+    products: Option<String>,
+    // compute only the masks of this shard
+    shard: Shard,
+    // report the progress on stderr
+    progress: bool,
+}
+
+fn main() {
+    let mut args = std::env::args().skip(1).collect::<Vec<_>>();
+    let mut table = DEFAULT_TABLE.to_owned();
+    let mut torsion = false;
+    let mut certificate = None;
+    let mut threads = std::thread::available_parallelism().map_or(1, NonZero::get);
+    let mut checkpoint = None;
+    let mut checkpoint_cache = false;
+    let mut format = Format::Text;
+    let mut input = None;
+    let mut compare = None;
+    let mut coefficients = Coefficients::Modular;
+    let mut chow = false;
+    let mut ck = false;
+    let mut ideal = None;
+    let mut matrices = false;
+    let mut cache_budget = None;
+    let mut cache_statistics = false;
+    let mut products = None;
+    let mut shard = Shard::ALL;
+    let mut progress = false;
+    while args.first().is_some_and(|arg| arg.starts_with("--")) {
+        match args.remove(0).as_str() {
+            // read the formula tables from a file generated by `generate-exact --table`
+            "--table" => {
+                assert!(!args.is_empty(), "missing argument");
+                let path = args.remove(0);
+                table = std::fs::read_to_string(&path)
+                    .unwrap_or_else(|e| panic!("cannot read {path}: {e}"));
+            }
+            "--torsion" => torsion = true,
+            "--certificate" => {
+                assert!(!args.is_empty(), "missing argument");
+                certificate = Some(args.remove(0));
+                torsion = true;
+            }
+            "--threads" => {
+                threads = args
+                    .first()
+                    .expect("missing argument")
+                    .parse()
+                    .expect("invalid number of threads");
+                args.remove(0);
+                assert!(threads >= 1, "at least one thread is needed");
+            }
+            "--checkpoint" => {
+                assert!(!args.is_empty(), "missing argument");
+                checkpoint = Some(args.remove(0));
+            }
+            "--checkpoint-cache" => checkpoint_cache = true,
+            // the memory budget of the multiplication cache in MiB
+            "--cache-budget" => {
+                let budget = args
+                    .first()
+                    .expect("missing argument")
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|budget| budget.checked_mul(1 << 20))
+                    .filter(|budget| *budget != 0)
+                    .expect("invalid cache budget");
+                args.remove(0);
+                cache_budget = Some(budget);
+            }
+            "--cache-statistics" => cache_statistics = true,
+            "--shard" => {
+                assert!(!args.is_empty(), "missing argument");
+                shard = Shard::parse(&args.remove(0)).unwrap_or_else(|e| panic!("{e}"));
+            }
+            // merge the outputs of the shards given as the remaining arguments
+            "--merge" => {
+                let outputs = args
+                    .drain(..)
+                    .map(|path| {
+                        let s = std::fs::read_to_string(&path)
+                            .unwrap_or_else(|e| panic!("cannot read {path}: {e}"));
+                        (path, s)
+                    })
+                    .collect::<Vec<_>>();
+                let merged = shard::merge(&outputs).unwrap_or_else(|e| panic!("{e}"));
+                print!("{merged}");
+                return;
+            }
+            "--progress" => progress = true,
+            "--products" => {
+                assert!(!args.is_empty(), "missing argument");
+                products = Some(args.remove(0));
+            }
+            "--input" => {
+                assert!(!args.is_empty(), "missing argument");
+                input = Some(args.remove(0));
+            }
+            "--compare" => {
+                assert!(!args.is_empty(), "missing argument");
+                compare = Some(args.remove(0));
+            }
+            "--format" => {
+                assert!(!args.is_empty(), "missing argument");
+                format = match args.remove(0).as_str() {
+                    "text" => Format::Text,
+                    "jsonl" => Format::Jsonl,
+                    format => panic!("unknown output format {format}"),
+                };
+            }
+            "--chow" => chow = true,
+            "--ck" => ck = true,
+            "--matrices" => matrices = true,
+            // further generators of the truncation ideal, such as `v1^6,v2^2`
+            "--ideal" => {
+                assert!(!args.is_empty(), "missing argument");
+                ideal = Some(args.remove(0));
+            }
+            "--coefficients" => {
+                assert!(!args.is_empty(), "missing argument");
+                coefficients = match args.remove(0).as_str() {
+                    "modular" => Coefficients::Modular,
+                    "f2" => Coefficients::F2,
+                    "integer" => Coefficients::Integer,
+                    "local" => Coefficients::Local,
+                    coefficients => panic!("unknown coefficients {coefficients}"),
+                };
+            }
+            arg => panic!("unknown option {arg}"),
         }
-        write!(
-            f,
-            "z_{{{}}}",
-            (1..=ZMask::BITS)
-                .filter(|i| self.zs & (1 << (i - 1)) != 0)
-                .map(|i| i.to_string())
-                .collect::<Vec<_>>()
-                .join(",")
-        )
     }
-}
+    let table = Table::parse(&table).unwrap_or_else(|e| panic!("invalid formula table: {e}"));
+    assert!(
+        ck || !table.connective,
+        "the formula tables only describe connective K-theory, which requires --ck",
+    );
+    let table = if ck { table.to_connective() } else { table };
+    let n = args
+        .first()
+        .expect("missing argument")
+        .parse()
+        .expect("invalid number");
+    let truncation = args.get(1).map_or(table.order, |arg| {
+        assert!(!chow, "--chow cannot be combined with a truncation order");
+        arg.parse().expect("invalid truncation order")
+    });
+    // only the parts of the formulas without v's are needed for the Chow ring
+    let truncation = if chow { 0 } else { truncation };
+    assert!(
+        truncation <= table.order,
+        "the formula tables are only available up to degree {} in the v's",
+        table.order,
+    );
+    assert!(
+        !chow || ideal.is_none(),
+        "--chow cannot be combined with --ideal"
+    );
+    let truncation = match ideal {
+        Some(ideal) => Truncation::parse(truncation, &ideal)
+            .unwrap_or_else(|e| panic!("invalid truncation ideal: {e}")),
+        None => Truncation::order(truncation),
+    };
+    assert!(
+        !checkpoint_cache || checkpoint.is_some(),
+        "--checkpoint-cache requires --checkpoint",
+    );
+    assert!(
+        !checkpoint_cache || !matrices,
+        "--checkpoint-cache cannot be combined with --matrices",
+    );
+    assert!(
+        input.is_none() || checkpoint.is_none(),
+        "--input cannot be combined with --checkpoint",
+    );
+    assert!(
+        compare.is_none() || format == Format::Text,
+        "--compare only supports the text format",
+    );
+    assert!(
+        coefficients == Coefficients::Modular
+            || (input.is_none() && checkpoint.is_none() && compare.is_none() && products.is_none()),
+        "--input, --checkpoint, --compare and --products require the coefficients modulo 2^t",
+    );
+    assert!(
+        !chow
+            || (input.is_none()
+                && checkpoint.is_none()
+                && compare.is_none()
+                && certificate.is_none()),
+        "--chow cannot be combined with --input, --checkpoint, --compare or --certificate",
+    );
+    assert!(
+        shard == Shard::ALL
+            || (format == Format::Text
+                && !torsion
+                && !chow
+                && input.is_none()
+                && compare.is_none()),
+        "--shard only supports printing the rational elements in the text format",
+    );
+    let options = Options {
+        table,
+        truncation,
+        torsion,
+        certificate,
+        threads,
+        format,
+        input,
+        checkpoint,
+        checkpoint_cache,
+        compare,
+        coefficients,
+        chow,
+        ck,
+        matrices,
+        cache_budget,
+        cache_statistics,
+        products,
+        shard,
+        progress,
+    };
 
-type Element = HashMap<BasisElement, Coefficient, BuildHasherDefault<FxHasher>>;
+    assert!(n >= 1);
+    // choose the integer types wide enough for n
+    match n {
+        0..=32 => run_with_mask::<u32>(n, &options),
+        33..=64 => run_with_mask::<u64>(n, &options),
+        65..=128 => run_with_mask::<u128>(n, &options),
+        _ => panic!("n > 128 is not supported"),
+    }
+}
 
-fn term(
-    c: Coefficient,
-    v1: Exponent,
-    v2: Exponent,
-    zs: &[u8],
-    n: u8,
-) -> Option<(BasisElement, Coefficient)> {
-    zs.iter().all(|i| *i <= n).then(|| {
-        (
-            BasisElement {
-                v1,
-                v2,
-                zs: zs.iter().map(|i| 1 << (i - 1)).fold(0, BitOr::bitor),
-            },
-            c,
-        )
-    })
+fn run_with_mask<M: ZMask>(n: u8, options: &Options) {
+    let t = torsion_exponent(n);
+    match options.coefficients {
+        Coefficients::Modular => match t {
+            0..=15 => run::<M, i32>(n, t, options),
+            16..=31 => run::<M, i64>(n, t, options),
+            32..=63 => run::<M, i128>(n, t, options),
+            _ => panic!("torsion exponents t > 63 are not supported"),
+        },
+        // the torsion analysis is then performed modulo 2
+        Coefficients::F2 => run::<M, F2>(n, 1, options),
+        // the torsion analysis still reduces modulo 2^t
+        Coefficients::Integer => {
+            assert!(t <= 63, "torsion exponents t > 63 are not supported");
+            run::<M, Integer>(n, t, options)
+        }
+        Coefficients::Local => {
+            assert!(t <= 63, "torsion exponents t > 63 are not supported");
+            run::<M, Local>(n, t, options)
+        }
+    }
 }
 
-fn multiply_monomial_z(
-    m: BasisElement,
-    k: u8,
-    t: u8,
-    squares: &HashMap<u8, Element>,
-    cache: &mut HashMap<(BasisElement, u8), Element>,
-) -> Element {
-    if cache.contains_key(&(m, k)) {
-        return cache[&(m, k)].clone();
+fn run<M: ZMask, C: Coefficient>(n: u8, t: u8, options: &Options) {
+    let Options {
+        ref table,
+        ref truncation,
+        ..
+    } = *options;
+    let cache = options
+        .cache_budget
+        .map_or_else(Cache::new, Cache::with_budget);
+    let ring = Ring::<M, C>::new(table, n, t, truncation.clone()).with_cache(cache);
+    let parameters = products::Parameters {
+        n,
+        t,
+        connective: options.ck,
+        truncation: truncation.clone(),
+        checksum: table.checksum,
+    };
+    if let Some(path) = &options.products {
+        products::load(path, &parameters, ring.cache()).unwrap_or_else(|e| panic!("{e}"));
     }
-    let result = if m.zs & (1 << (k - 1)) == 0 {
-        Element::from_iter(std::iter::once((
-            BasisElement {
-                v1: m.v1,
-                v2: m.v2,
-                zs: m.zs | (1 << (k - 1)),
-            },
-            1,
-        )))
+
+    let d = u16::from(n) * u16::from(n + 1) / 2;
+    // the lowest degree in which the truncation does not lose any information
+    let lowest = if options.chow {
+        0
     } else {
-        multiply_element_monomial(
-            squares[&k].clone(),
-            BasisElement {
-                v1: m.v1,
-                v2: m.v2,
-                zs: m.zs & !(1 << (k - 1)),
-            },
-            t,
-            squares,
-            cache,
-        )
+        d.saturating_sub(truncation.exact_order().into())
+    };
+    // the computed rational elements u^{d1}c_{cs}^*, if needed for the torsion analysis
+    let mut rational = Vec::new();
+    // the exact rational elements not compared yet, the number of compared ones, and the number of
+    // them differing from the computed ones
+    let mut exact = options.compare.as_ref().map(|path| {
+        let s = std::fs::read_to_string(path).unwrap_or_else(|e| panic!("cannot read {path}: {e}"));
+        let mut exact = compare::parse::<M, C>(&s, n, t, truncation)
+            .unwrap_or_else(|e| panic!("invalid exact results {path}: {e}"));
+        // the exact results may also be those of the full Brown-Peterson cohomology
+        if options.ck {
+            for y in exact.values_mut() {
+                y.retain(|m, _| (m.v2, m.v3, m.v4) == (0, 0, 0));
+            }
+        }
+        exact
+    });
+    let mut compared = 0;
+    let mut mismatches = 0;
+    let mut print = |mask: u128, results: Results<M, C>| {
+        let cs = chern_classes(mask, n);
+        for (d1, x) in results {
+            match (&mut exact, options.format) {
+                (None, _) if options.chow => {}
+                (Some(exact), _) => {
+                    let name = output::name(d1, &cs);
+                    compared += 1;
+                    match exact.remove(&(d1, cs.clone())) {
+                        Some(y) => {
+                            let differences =
+                                compare::differences(&x.iter().cloned().collect(), &y);
+                            for (m, c1, c2) in &differences {
+                                println!("{name}: coefficient {c1} instead of {c2} at {m}");
+                            }
+                            if !differences.is_empty() {
+                                mismatches += 1;
+                            }
+                        }
+                        None => {
+                            println!("{name}: no exact result");
+                            mismatches += 1;
+                        }
+                    }
+                }
+                (None, Format::Text) => println!("{}", output::line(d1, &cs, &x)),
+                (None, Format::Jsonl) => println!(
+                    "{{\"n\":{n},\"t\":{t},\"d1\":{d1},\"cs\":[{}],\"terms\":[{}]}}",
+                    cs.iter()
+                        .map(ToString::to_string)
+                        .collect::<Vec<_>>()
+                        .join(","),
+                    x.iter()
+                        .map(|(m, c)| format!(
+                            "{{\"v1\":{},\"v2\":{},\"v3\":{},\"v4\":{},\"zs\":[{}],\"c\":{c}}}",
+                            m.v1,
+                            m.v2,
+                            m.v3,
+                            m.v4,
+                            m.indices()
+                                .map(|i| i.to_string())
+                                .collect::<Vec<_>>()
+                                .join(","),
+                        ))
+                        .collect::<Vec<_>>()
+                        .join(","),
+                ),
+            }
+            if options.torsion || options.chow {
+                rational.push((d1, cs.clone(), x));
+            }
+        }
     };
-    cache.insert((m, k), result.clone());
-    result
-}
 
-fn multiply_monomial_monomial(
-    m1: BasisElement,
-    m2: BasisElement,
-    t: u8,
-    squares: &HashMap<u8, Element>,
-    cache: &mut HashMap<(BasisElement, u8), Element>,
-) -> Element {
-    if m2.zs == 0 {
-        let v1 = m1.v1 + m2.v1;
-        let v2 = m1.v2 + m2.v2;
-        if v1 + 3 * v2 > 4 {
-            Element::default()
-        } else {
-            Element::from_iter(std::iter::once((BasisElement { v1, v2, zs: m1.zs }, 1)))
+    // the results from a previous run are printed again, and only the remaining masks computed
+    let mut remaining = 1_u128 << (n - 1);
+    if options.shard != Shard::ALL {
+        println!("{}", options.shard.header(&parameters.to_string()));
+    }
+    if let Some(path) = &options.input {
+        let s = std::fs::read_to_string(path).unwrap_or_else(|e| panic!("cannot read {path}: {e}"));
+        let mut blocks = Vec::<(u128, Results<M, C>)>::new();
+        for ((d1, cs), x) in
+            output::parse::<M, C>(&s, n, t).unwrap_or_else(|e| panic!("invalid input {path}: {e}"))
+        {
+            let mask = cs.iter().map(|i| 1 << (i - 2)).sum();
+            let mut x = x.into_iter().collect::<SortedElement<M, C>>();
+            x.sort_unstable_by_key(|(m, _)| *m);
+            match blocks.last_mut() {
+                Some((mask2, results)) if *mask2 == mask => results.push((d1, x)),
+                _ => blocks.push((mask, vec![(d1, x)])),
+            }
         }
-    } else {
-        let k = u8::try_from(m2.zs.ilog2() + 1).unwrap();
-        multiply_element_monomial(
-            multiply_monomial_z(m1, k, t, squares, cache),
-            BasisElement {
-                v1: m2.v1,
-                v2: m2.v2,
-                zs: m2.zs & !(1 << (k - 1)),
-            },
-            t,
-            squares,
-            cache,
-        )
+        for (mask, results) in blocks {
+            print(mask, results);
+        }
+        remaining = 0;
     }
-}
+    let mut checkpoint = options.checkpoint.as_ref().map(|path| {
+        let (checkpoint, results) = Checkpoint::open(
+            path,
+            &parameters,
+            options.shard,
+            options.checkpoint_cache.then_some(ring.cache()),
+        );
+        for (mask, results) in results {
+            print(mask, results);
+            remaining = mask;
+        }
+        checkpoint
+    });
 
-fn multiply_element_monomial(
-    e: Element,
-    m2: BasisElement,
-    t: u8,
-    squares: &HashMap<u8, Element>,
-    cache: &mut HashMap<(BasisElement, u8), Element>,
-) -> Element {
-    let mut result = Element::default();
-    for (m1, c1) in e {
-        for (m, c) in multiply_monomial_monomial(m1, m2, t, squares, cache) {
-            let entry = result.entry(m).or_insert(0);
-            *entry = (*entry + c1 * c) & ((1 << t) - 1);
+    // The masks are handed out to the threads in descending order, and the results are printed
+    // in the same order as soon as all previous ones are available. The terms are sorted, since
+    // the order of the hash maps depends on the state of the cache. The products c_{cs}^* are
+    // reused between the masks.
+    let masks = Masks::new(&ring, remaining, options.shard);
+    let mut progress = options
+        .progress
+        .then(|| Progress::new(n, truncation.order, options.shard, remaining));
+    // computed in a separate ring, whose cache is not needed afterwards
+    let matrices = (options.matrices && remaining != 0)
+        .then(|| Matrices::new(&Ring::<M, C>::new(table, n, t, truncation.clone())));
+    let (sender, receiver) = mpsc::channel();
+    std::thread::scope(|scope| {
+        for _ in 0..options.threads {
+            let sender = sender.clone();
+            let (masks, ring, matrices) = (&masks, &ring, &matrices);
+            scope.spawn(move || {
+                while let Some((mask, mut x)) = masks.next() {
+                    let cs = chern_classes(mask, n);
+                    let degree = cs.iter().copied().map(u16::from).sum::<u16>();
+                    // u^{d1}c_{cs}^* = u^{d1-1}c_{cs}^*u, computed in the same way as from scratch
+                    let u = ring.u();
+                    let results = match matrices {
+                        Some(matrices) => {
+                            let u = matrices.vector(&u);
+                            let mut x = matrices.vector(&x);
+                            (0..=d - degree)
+                                .filter_map(|d1| {
+                                    if d1 != 0 {
+                                        x = matrices.multiply(&u, &x);
+                                    }
+                                    (d1 + degree >= lowest).then(|| (d1, matrices.terms(&x)))
+                                })
+                                .collect::<Results<M, C>>()
+                        }
+                        None => (0..=d - degree)
+                            .filter_map(|d1| {
+                                if d1 != 0 {
+                                    x = &x * &u;
+                                }
+                                (d1 + degree >= lowest).then(|| (d1, x.terms()))
+                            })
+                            .collect(),
+                    };
+                    sender.send((mask, results)).unwrap();
+                }
+            });
         }
-    }
-    result.retain(|_, c| *c != 0);
-    result
-}
+        drop(sender);
 
-fn multiply_element_element(
-    e1: Element,
-    e2: Element,
-    t: u8,
-    squares: &HashMap<u8, Element>,
-    cache: &mut HashMap<(BasisElement, u8), Element>,
-) -> Element {
-    let mut result = Element::default();
-    for (m2, c2) in e2 {
-        for (m, c) in multiply_element_monomial(e1.clone(), m2, t, squares, cache) {
-            let entry = result.entry(m).or_insert(0);
-            *entry = (*entry + c * c2) & ((1 << t) - 1);
+        let mut pending = BTreeMap::new();
+        let mut next = options.shard.previous(remaining);
+        for (mask, results) in &receiver {
+            if let Some(progress) = &mut progress {
+                progress.record(mask, results.len(), ring.cache());
+            }
+            pending.insert(mask, results);
+            while let Some(results) = next.and_then(|mask| pending.remove(&mask)) {
+                let mask = next.unwrap();
+                next = options.shard.previous(mask);
+                if let Some(checkpoint) = &mut checkpoint {
+                    checkpoint.record(mask, &results);
+                    checkpoint.save_cache(ring.cache());
+                }
+                print(mask, results);
+            }
+        }
+    });
+    if options.shard != Shard::ALL {
+        println!("{}", options.shard.trailer());
+    }
+    if options.cache_statistics {
+        eprintln!("multiplication cache: {}", ring.cache().statistics());
+    }
+    // only if anything new was computed
+    if let Some(path) = &options.products {
+        if ring.cache().statistics().misses != 0 {
+            products::save(path, &parameters, ring.cache()).unwrap_or_else(|e| panic!("{e}"));
+        }
+    }
+    if let Some(exact) = exact {
+        for (d1, cs) in exact.keys() {
+            println!("{}: not computed", output::name(*d1, cs));
+        }
+        println!(
+            "{} of {} rational elements differ from the exact ones",
+            mismatches + exact.len(),
+            compared + exact.len(),
+        );
+    }
+    if options.torsion || options.chow {
+        let certificates = report_torsion(
+            &rational,
+            n,
+            t,
+            truncation.exact_order(),
+            options.chow,
+            options.ck,
+            options.format,
+        );
+        if let Some(path) = &options.certificate {
+            std::fs::write(path, certificates)
+                .unwrap_or_else(|e| panic!("cannot write {path}: {e}"));
         }
     }
-    result.retain(|_, c| *c != 0);
-    result
 }
 
-fn rational_element(
-    k: Exponent,
-    cs: &[u8],
+// Print the degrees in which the quotient of the truncated cohomology by the rational elements has
+// torsion, together with the elementary divisors, and return certificates for the torsion. Only
+// the degrees of at least d-truncation are considered, since below the truncation loses
+// information. In each of them, the rational elements are spanned by the v-multiples of the
+// computed ones of the same or higher degree.
+//
+// In the Chow ring, nothing is lost, so all degrees are considered, and the rational elements are
+// spanned by the computed ones of the same degree. The degrees in which they do not span the Chow
+// ring modulo 2 are printed as candidates in addition, since elsewhere the quotient vanishes.
+//
+// In connective K-theory, only the v1-multiples are needed.
+fn report_torsion<M: ZMask, C: Coefficient>(
+    rational: &[(u16, Vec<u8>, SortedElement<M, C>)],
     n: u8,
     t: u8,
-    squares: &HashMap<u8, Element>,
-    cache: &mut HashMap<(BasisElement, u8), Element>,
-) -> Element {
-    let u = Element::from_iter(
-        [
-            term(-1, 0, 0, &[1], n),
-            term(-1, 2, 0, &[1, 2], n),
-            term(5, 3, 0, &[4], n),
-            term(-1, 3, 0, &[1, 3], n),
-            term(4, 0, 1, &[4], n),
-            term(-1, 0, 1, &[1, 3], n),
-            term(-4, 4, 0, &[5], n),
-            term(-6, 4, 0, &[1, 4], n),
-            term(-1, 4, 0, &[2, 3], n),
-            term(-6, 1, 1, &[5], n),
-            term(-8, 1, 1, &[1, 4], n),
-            term(1, 1, 1, &[2, 3], n),
-        ]
-        .into_iter()
-        .flatten(),
+    truncation: Exponent,
+    chow: bool,
+    ck: bool,
+    format: Format,
+) -> String {
+    let d = u16::from(n) * u16::from(n + 1) / 2;
+    let mut certificates = format!(
+        "# Certificates for torsion in the module of irrational elements of the Brown-Peterson
+# cohomology of OGr(n) modulo 2^t, up to degree N in the v's. Generated by `approximate`, and
+# checked by `generate-exact --verify`.
+#
+# In the following, v^e = v1^e1*…*v4^e4.
+# `torsion D k`: the following lines describe an element y of degree D, whose class in the
+#   quotient by the rational elements has order 2^k
+# `combination a e1 e2 e3 e4 d1 i…`: 2^k*y is the sum of the a*v^e*u^{{d1}}*c_{{i…}}^*
+# `class c e1 e2 e3 e4 i…`: y contains c*v^e*z_{{i…}}
+# `witness c e1 e2 e3 e4 i…`: the linear form taking the value c on v^e*z_{{i…}} and vanishing on
+#   all other basis elements is 1 on y and divisible by 2^k on all rational elements of degree D
+# `connective`: if present, everything happens in connective K-theory, where all v's except v1
+#   vanish
+n {n}
+t {t}
+order {truncation}
+{}",
+        if ck { "connective\n" } else { "" },
     );
-    std::iter::repeat_n(u, k.into())
-        .chain(cs.iter().map(|i| {
-            [
-                term(2, 0, 0, &[*i], n),
-                term(-1, 1, 0, &[i + 1], n),
-                term(2, 2, 0, &[i + 2], n),
-                term(-8, 3, 0, &[i + 3], n),
-                term(-7, 0, 1, &[i + 3], n),
-                term(26, 4, 0, &[i + 4], n),
-                term(30, 1, 1, &[i + 4], n),
-            ]
-            .into_iter()
-            .flatten()
-            .map(|(m, c)| {
-                (
-                    m,
-                    (Coefficient::pow(-1, (i + 1).into()) * c) & ((1 << t) - 1),
-                )
-            })
-            .filter(|(m, c)| *c != 0 && m.zs == m.zs & ((1 << n) - 1))
-            .collect()
-        }))
-        .rfold(Element::from_iter(term(1, 0, 0, &[], n)), |accum, e| {
-            multiply_element_element(accum, e, t, squares, cache)
-        })
-}
-
-fn torsion_exponent(n: u8) -> u8 {
-    if n == 0 {
-        return 0;
+    let mut found = false;
+    let lowest = if chow {
+        0
+    } else {
+        d.saturating_sub(truncation.into())
+    };
+    // the number of basis elements of the Chow ring in each degree, i.e. of sets of z's
+    let mut dimensions = vec![0_u64; usize::from(d) + 1];
+    dimensions[0] = 1;
+    for i in 1..=usize::from(n) {
+        for degree in (i..dimensions.len()).rev() {
+            dimensions[degree] += dimensions[degree - i];
+        }
     }
-    let n_ = u16::from(n);
-    let temp = n_ - u16::try_from((n_ * (n_ + 1) / 2 + 1).ilog2()).unwrap();
-    let e = u8::try_from(n.ilog2()).unwrap();
-    let b = n - (1 << e);
-    let exceptional = 2 * b + 3 <= e + torsion_exponent(b);
-    u8::try_from(temp).unwrap() + if exceptional { 1 } else { 0 }
-}
-
-fn main() {
-    let n = std::env::args()
-        .nth(1)
-        .expect("missing argument")
-        .parse()
-        .expect("invalid number");
-
-    let t = torsion_exponent(n);
-    let squares = std::iter::once((
-        1,
-        [
-            term(1, 0, 0, &[2], n),
-            term(1, 1, 0, &[3], n),
-            term(2, 2, 0, &[4], n),
-            term(-2, 2, 0, &[1, 3], n),
-            term(1, 3, 0, &[5], n),
-            term(4, 3, 0, &[1, 4], n),
-            term(1, 3, 0, &[2, 3], n),
-            term(-1, 0, 1, &[5], n),
-            term(4, 0, 1, &[1, 4], n),
-            term(1, 0, 1, &[2, 3], n),
-            term(16, 4, 0, &[6], n),
-            term(-26, 4, 0, &[1, 5], n),
-            term(17, 1, 1, &[6], n),
-            term(-32, 1, 1, &[1, 5], n),
-            term(2, 1, 1, &[2, 4], n),
-        ]
-        .into_iter()
-        .flatten()
-        .map(|(m, c)| (m, c & ((1 << t) - 1)))
-        .filter(|(m, c)| *c != 0 && m.zs == m.zs & ((1 << n) - 1))
-        .collect(),
-    ))
-    .chain(std::iter::once((
-        2,
-        [
-            term(1, 0, 0, &[4], n),
-            term(-2, 0, 0, &[1, 3], n),
-            term(2, 1, 0, &[5], n),
-            term(-2, 1, 0, &[1, 4], n),
-            term(1, 1, 0, &[2, 3], n),
-            term(2, 2, 0, &[6], n),
-            term(-1, 2, 0, &[2, 4], n),
-            term(6, 3, 0, &[1, 6], n),
-            term(-8, 3, 0, &[2, 5], n),
-            term(7, 3, 0, &[3, 4], n),
-            term(2, 0, 1, &[1, 6], n),
-            term(-4, 0, 1, &[2, 5], n),
-            term(6, 0, 1, &[3, 4], n),
-            term(7, 4, 0, &[8], n),
-            term(-8, 4, 0, &[1, 7], n),
-            term(7, 4, 0, &[2, 6], n),
-            term(-12, 4, 0, &[3, 5], n),
-            term(13, 1, 1, &[8], n),
-            term(-20, 1, 1, &[1, 7], n),
-            term(17, 1, 1, &[2, 6], n),
-            term(-18, 1, 1, &[3, 5], n),
-        ]
-        .into_iter()
-        .flatten()
-        .map(|(m, c)| (m, (-c) & ((1 << t) - 1)))
-        .filter(|(m, c)| *c != 0 && m.zs == m.zs & ((1 << n) - 1))
-        .collect(),
-    )))
-    .chain((3..=n).map(|k| {
-        let k_ = Coefficient::from(k);
-        (
-            k,
-            term(1, 0, 0, &[2 * k], n)
+    for degree in lowest..=d {
+        let mut columns = HashMap::new();
+        // the rows, and the rational elements and monomials in the v's they come from
+        let mut rows = Vec::new();
+        let mut generators = Vec::new();
+        for (i, (d1, cs, x)) in rational.iter().enumerate() {
+            let degree2 = d1 + cs.iter().copied().map(u16::from).sum::<u16>();
+            if degree2 < degree || degree2 - degree > truncation.into() {
+                continue;
+            }
+            for [e1, e2, e3, e4] in v_monomials(degree2 - degree)
                 .into_iter()
-                .chain((1..=k - 1).flat_map(|i| {
-                    term(Coefficient::pow(-1, i.into()) * 2, 0, 0, &[i, 2 * k - i], n)
-                }))
-                .chain(term(k_, 1, 0, &[2 * k + 1], n))
-                .chain(term(-(2 * k_ - 2), 1, 0, &[1, 2 * k], n))
-                .chain((2..=k).flat_map(|i| {
-                    let i_ = Coefficient::from(i);
-                    term(
-                        Coefficient::pow(-1, i.into()) * (2 * (k_ - i_) + 1),
-                        1,
-                        0,
-                        &[i, 2 * k + 1 - i],
-                        n,
-                    )
-                }))
-                .chain(term((k_ * k_ + k_ - 2) / 2, 2, 0, &[2 * k + 2], n))
-                .chain(term(-(k_ * k_ - k_ - 2), 2, 0, &[1, 2 * k + 1], n))
-                .chain(term(k_ * k_ - 2 * k_ - 1, 2, 0, &[2, 2 * k], n))
-                .chain((3..=k).flat_map(|i| {
-                    let i_ = Coefficient::from(i);
-                    term(
-                        Coefficient::pow(-1, i.into())
-                            * ((k_ - i_) * (k_ - i_) + 2 * (k_ - i_) + 1),
-                        2,
-                        0,
-                        &[i, 2 * k + 2 - i],
-                        n,
-                    )
-                }))
-                .chain(term(
-                    (k_ * k_ * k_ + 3 * k_ * k_ + 2 * k_ - 24) / 6,
-                    3,
-                    0,
-                    &[2 * k + 3],
-                    n,
-                ))
-                .chain(term(
-                    -(k_ * k_ * k_ - k_ - 24) / 3,
-                    3,
-                    0,
-                    &[1, 2 * k + 2],
-                    n,
-                ))
-                .chain(term(
-                    (2 * k_ * k_ * k_ - 3 * k_ * k_ + k_ - 54) / 6,
-                    3,
-                    0,
-                    &[2, 2 * k + 1],
-                    n,
-                ))
-                .chain(term(
-                    -(2 * k_ * k_ * k_ - 9 * k_ * k_ + 25 * k_ - 72) / 6,
-                    3,
-                    0,
-                    &[3, 2 * k],
-                    n,
-                ))
-                .chain((4..=k + 1).flat_map(|i| {
-                    let i_ = Coefficient::from(i);
-                    term(
-                        Coefficient::pow(-1, i.into())
-                            * (2 * (k_ - i_) * (k_ - i_) * (k_ - i_)
-                                + 9 * (k_ - i_) * (k_ - i_)
-                                + 25 * (k_ - i_)
-                                + 24)
-                            / 6,
-                        3,
-                        0,
-                        &[i, 2 * k + 3 - i],
-                        n,
-                    )
-                }))
-                .chain(term(k_ - 2, 0, 1, &[2 * k + 3], n))
-                .chain(term(-(2 * k_ - 6), 0, 1, &[1, 2 * k + 2], n))
-                .chain(term(2 * k_ - 8, 0, 1, &[2, 2 * k + 1], n))
-                .chain(term(-(2 * k_ - 10), 0, 1, &[3, 2 * k], n))
-                .chain((4..=k + 1).flat_map(|i| {
-                    let i_ = Coefficient::from(i);
-                    term(
-                        Coefficient::pow(-1, i.into()) * (2 * (k_ - i_) + 3),
-                        0,
-                        1,
-                        &[i, 2 * k + 3 - i],
-                        n,
-                    )
-                }))
-                .chain(term(
-                    (2 * k_ * k_ * k_ * k_ + 12 * k_ * k_ * k_ + 46 * k_ * k_ - 108 * k_ - 1008)
-                        / 48,
-                    4,
-                    0,
-                    &[2 * k + 4],
-                    n,
-                ))
-                .chain(term(
-                    -(k_ * k_ * k_ * k_ + 2 * k_ * k_ * k_ + 11 * k_ * k_ - 86 * k_ - 432) / 12,
-                    4,
-                    0,
-                    &[1, 2 * k + 3],
-                    n,
-                ))
-                .chain(term(
-                    (k_ * k_ * k_ * k_ + 11 * k_ * k_ - 108 * k_ - 384) / 12,
-                    4,
-                    0,
-                    &[2, 2 * k + 2],
-                    n,
-                ))
-                .chain(term(
-                    -(k_ * k_ * k_ * k_ - 4 * k_ * k_ * k_ + 29 * k_ * k_ - 146 * k_ - 288) / 12,
-                    4,
-                    0,
-                    &[3, 2 * k + 1],
-                    n,
-                ))
-                .chain(term(
-                    (k_ * k_ * k_ * k_ - 8 * k_ * k_ * k_ + 47 * k_ * k_ - 124 * k_ - 204) / 12,
-                    4,
-                    0,
-                    &[4, 2 * k],
-                    n,
-                ))
-                .chain((5..=k + 1).flat_map(|i| {
-                    let i_ = Coefficient::from(i);
-                    term(
-                        Coefficient::pow(-1, i.into())
-                            * ((k_ - i_) * (k_ - i_) * (k_ - i_) * (k_ - i_)
-                                + 8 * (k_ - i_) * (k_ - i_) * (k_ - i_)
-                                + 47 * (k_ - i_) * (k_ - i_)
-                                + 124 * (k_ - i_)
-                                + 108)
-                            / 12,
-                        4,
-                        0,
-                        &[i, 2 * k + 4 - i],
-                        n,
-                    )
-                }))
-                .chain(term(k_ * k_ - 21, 1, 1, &[2 * k + 4], n))
-                .chain(term(-(2 * k_ * k_ - 4 * k_ - 40), 1, 1, &[1, 2 * k + 3], n))
-                .chain(term(2 * k_ * k_ - 7 * k_ - 37, 1, 1, &[2, 2 * k + 2], n))
-                .chain(term(
-                    -(2 * k_ * k_ - 11 * k_ - 28),
-                    1,
-                    1,
-                    &[3, 2 * k + 1],
-                    n,
-                ))
-                .chain(term(2 * k_ * k_ - 8 * k_ - 22, 1, 1, &[4, 2 * k], n))
-                .chain((5..=k + 1).flat_map(|i| {
-                    let i_ = Coefficient::from(i);
-                    term(
-                        Coefficient::pow(-1, i.into())
-                            * (2 * (k_ - i_) * (k_ - i_) + 8 * (k_ - i_) + 8),
-                        1,
-                        1,
-                        &[i, 2 * k + 4 - i],
-                        n,
-                    )
-                }))
-                .map(|(m, c)| {
-                    (
-                        m,
-                        (Coefficient::pow(-1, (k + 1).into()) * c) & ((1 << t) - 1),
-                    )
-                })
-                .filter(|(m, c)| *c != 0 && m.zs == m.zs & ((1 << n) - 1))
-                .collect(),
-        )
-    }))
-    .collect();
+                .filter(|e| !ck || e[1..] == [0; 3])
+            {
+                rows.push(
+                    x.iter()
+                        .map(|(m, c)| {
+                            let m = BasisElement {
+                                v1: m.v1 + e1,
+                                v2: m.v2 + e2,
+                                v3: m.v3 + e3,
+                                v4: m.v4 + e4,
+                                zs: m.zs,
+                            };
+                            let len = columns.len();
+                            (*columns.entry(m).or_insert(len), c.residue(t))
+                        })
+                        .collect::<Vec<_>>(),
+                );
+                generators.push((i, [e1, e2, e3, e4]));
+            }
+        }
+        let mut basis = vec![None; columns.len()];
+        for (m, j) in columns {
+            basis[j] = Some(m);
+        }
+        let basis = basis.into_iter().map(Option::unwrap).collect::<Vec<_>>();
+        let rows = rows
+            .into_iter()
+            .map(|row| {
+                let mut dense = vec![0; basis.len()];
+                for (j, c) in row {
+                    dense[j] = c;
+                }
+                dense
+            })
+            .collect();
 
-    let mut cache = Default::default();
-    let d = u8::try_from(u16::from(n) * u16::from(n + 1) / 2).unwrap();
-    for mask in (0..1 << (n - 1)).rev() {
-        let cs = (2..=n)
-            .filter(|i| mask & (1 << (i - 2)) != 0)
+        let pivots = torsion::smith_normal_form(rows, t);
+        // In the Chow ring, all columns are sets of z's, so the unit pivots span the rational
+        // classes modulo 2. Otherwise, the columns also contain the v-multiples.
+        if chow {
+            let irrational = dimensions[usize::from(degree)]
+                - u64::try_from(pivots.iter().filter(|pivot| pivot.exponent == 0).count()).unwrap();
+            if irrational != 0 {
+                match format {
+                    Format::Text => println!(
+                        "candidate degree {degree}: {irrational} of {} classes irrational modulo 2",
+                        dimensions[usize::from(degree)],
+                    ),
+                    Format::Jsonl => println!(
+                        "{{\"degree\":{degree},\"irrational\":{irrational},\"dimension\":{}}}",
+                        dimensions[usize::from(degree)],
+                    ),
+                }
+            }
+        }
+        let divisors = (0..pivots.len())
+            .filter(|k| 0 < pivots[*k].exponent && pivots[*k].exponent < t)
             .collect::<Vec<_>>();
-        for d1 in 0..=d - cs.iter().sum::<u8>() {
-            if d1 + cs.iter().sum::<u8>() >= d.saturating_sub(4) {
-                println!(
-                    "u^{{{d1}}}c_{{{}}}^*≡{}",
-                    cs.iter()
-                        .map(ToString::to_string)
-                        .collect::<Vec<_>>()
-                        .join(","),
-                    rational_element(d1, &cs, n, t, &squares, &mut cache)
-                        .into_iter()
-                        .map(|(m, c)| format!("{c}{m}"))
-                        .collect::<Vec<_>>()
-                        .join("+"),
+        if divisors.is_empty() {
+            continue;
+        }
+        found = true;
+        match format {
+            Format::Text => println!(
+                "torsion in degree {degree}: {}",
+                divisors
+                    .iter()
+                    .map(|k| format!("Z/2^{}", pivots[*k].exponent))
+                    .collect::<Vec<_>>()
+                    .join("⊕"),
+            ),
+            // the exponents e of the elementary divisors 2^e
+            Format::Jsonl => println!(
+                "{{\"degree\":{degree},\"torsion\":[{}]}}",
+                divisors
+                    .iter()
+                    .map(|k| pivots[*k].exponent.to_string())
+                    .collect::<Vec<_>>()
+                    .join(","),
+            ),
+        }
+        for k in divisors {
+            let pivot = &pivots[k];
+            certificates += &format!("torsion {degree} {}\n", pivot.exponent);
+            for (i, a) in &pivot.combination {
+                let (j, [e1, e2, e3, e4]) = generators[*i];
+                let (d1, cs, _) = &rational[j];
+                certificates += &format!(
+                    "combination {a} {e1} {e2} {e3} {e4} {d1}{}\n",
+                    cs.iter().map(|i| format!(" {i}")).collect::<String>(),
                 );
             }
+            for (m, c) in basis.iter().zip(&pivot.row) {
+                if *c != 0 {
+                    certificates += &format!("class {} {}\n", c >> pivot.exponent, m.format());
+                }
+            }
+            for (m, c) in basis.iter().zip(torsion::witness(&pivots, k, t)) {
+                if c != 0 {
+                    certificates += &format!("witness {c} {}\n", m.format());
+                }
+            }
         }
     }
+    if !found && format == Format::Text {
+        println!("no torsion found");
+    }
+    certificates
 }
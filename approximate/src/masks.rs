@@ -0,0 +1,87 @@
+// SPDX-FileCopyrightText: Alois Wohlschlager <wohlschlager@math.lmu.de>
+// SPDX-License-Identifier: EUPL-1.2
+
+//! The distribution of the masks to the threads computing the rational elements, together with
+//! the products c_{…}^* for them. Removing the smallest index from a mask gives a smaller mask, so
+//! the product for a mask is the one for the smaller mask times a single c_i^*, in the same order
+//! as in [`crate::rational_element`]. Since the masks are handed out in descending order, the
+//! product for the smaller mask is kept until the smaller mask itself is handed out. Only few
+//! products are kept at any time, as all larger masks containing a given one are handed out
+//! shortly before it. For a [`Shard`], only its masks are handed out, and the products for the
+//! other ones are dropped once the masks handed out are below them.
+
+use crate::{
+    ring::{Ring, RingElement},
+    shard::Shard,
+    Coefficient, ZMask,
+};
+use std::{collections::HashMap, sync::Mutex};
+
+pub struct Masks<'a, M, C> {
+    ring: &'a Ring<M, C>,
+    shard: Shard,
+    state: Mutex<State<'a, M, C>>,
+}
+
+struct State<'a, M, C> {
+    // the masks below this have not been handed out yet
+    next: u128,
+    // the products already computed for masks not handed out yet
+    known: HashMap<u128, RingElement<'a, M, C>>,
+}
+
+impl<'a, M: ZMask, C: Coefficient> Masks<'a, M, C> {
+    /// all masks of the shard below `end`, in descending order
+    pub fn new(ring: &'a Ring<M, C>, end: u128, shard: Shard) -> Self {
+        Masks {
+            ring,
+            shard,
+            state: Mutex::new(State {
+                next: end,
+                known: HashMap::new(),
+            }),
+        }
+    }
+
+    /// the next mask together with c_{…}^* for it, or `None` if all of them have been handed out
+    pub fn next(&self) -> Option<(u128, RingElement<'a, M, C>)> {
+        let mask = {
+            let mut state = self.state.lock().unwrap();
+            let mask = self.shard.previous(state.next)?;
+            state.next = mask;
+            // the products for the larger masks are not needed any more
+            state.known.retain(|m, _| *m <= mask);
+            mask
+        };
+        Some((mask, self.product(mask, true)))
+    }
+
+    // the product for the mask, which is handed out right now if `own` is set, and which is
+    // otherwise contained in such a mask
+    fn product(&self, mask: u128, own: bool) -> RingElement<'a, M, C> {
+        if mask == 0 {
+            return self.ring.one();
+        }
+        let known = {
+            let mut state = self.state.lock().unwrap();
+            if own {
+                state.known.remove(&mask)
+            } else {
+                state.known.get(&mask).cloned()
+            }
+        };
+        if let Some(x) = known {
+            return x;
+        }
+        let i = u8::try_from(mask.trailing_zeros() + 2).unwrap();
+        let x = &self.product(mask & (mask - 1), false) * &self.ring.dual(i);
+        if !own {
+            // once the mask is handed out, nobody needs the product any more
+            let mut state = self.state.lock().unwrap();
+            if mask < state.next {
+                state.known.insert(mask, x.clone());
+            }
+        }
+        x
+    }
+}
@@ -0,0 +1,205 @@
+// SPDX-FileCopyrightText: Alois Wohlschlager <wohlschlager@math.lmu.de>
+// SPDX-License-Identifier: EUPL-1.2
+
+//! An alternative multiplication for moderate n. The multiplications by the z_k are precomputed as
+//! sparse matrices on the basis of the truncated ring, so that products become chains of sparse
+//! matrix-vector products, without any hashing or cloning of basis elements. The basis elements
+//! are numbered by the mask of the z's and the monomial in the v's.
+
+use crate::{
+    ring::{Ring, RingElement},
+    v_monomials, BasisElement, Coefficient, Exponent, SortedElement, ZMask,
+};
+use std::{collections::HashMap, marker::PhantomData};
+
+/// the nonzero coordinates of an element in ascending order
+pub type Vector<C> = Vec<(usize, C)>;
+
+pub struct Matrices<M, C> {
+    n: u8,
+    t: u8,
+    // the monomials in the v's not dropped by the truncation, and their numbers
+    monomials: Vec<[Exponent; 4]>,
+    numbers: HashMap<[Exponent; 4], usize>,
+    // products[i][j] is the number of the product of the i-th and the j-th monomial, unless it is
+    // dropped
+    products: Vec<Vec<Option<usize>>>,
+    // z[k-1][j] is the j-th column of the multiplication by z_k
+    z: Vec<Vec<Vector<C>>>,
+    mask: PhantomData<M>,
+}
+
+impl<M: ZMask, C: Coefficient> Matrices<M, C> {
+    /// The matrices for the given ring. The products are computed in the ring, so that afterwards
+    /// its cache contains all of them, and it is best to use a separate ring dropped afterwards.
+    pub fn new(ring: &Ring<M, C>) -> Self {
+        let n = ring.n;
+        assert!(n <= 20, "the matrices are only available for n <= 20");
+        let monomials = (0..=u16::from(ring.truncation.order))
+            .flat_map(v_monomials)
+            .filter(|e| !ring.truncation.contains(e) && (!ring.connective || e[1..] == [0; 3]))
+            .collect::<Vec<_>>();
+        let numbers = monomials
+            .iter()
+            .enumerate()
+            .map(|(i, e)| (*e, i))
+            .collect::<HashMap<_, _>>();
+        let products = monomials
+            .iter()
+            .map(|e| {
+                monomials
+                    .iter()
+                    .map(|f| numbers.get(&[0, 1, 2, 3].map(|i| e[i] + f[i])).copied())
+                    .collect()
+            })
+            .collect();
+        let mut result = Matrices {
+            n,
+            t: ring.t,
+            monomials,
+            numbers,
+            products,
+            z: Vec::new(),
+            mask: PhantomData,
+        };
+        result.z = (1..=n)
+            .map(|k| {
+                let z = ring.z(k);
+                (0..result.len())
+                    .map(|j| {
+                        let m = ring.element([(result.basis_element(j), C::ONE)]);
+                        result.vector(&(&m * &z))
+                    })
+                    .collect()
+            })
+            .collect();
+        result
+    }
+
+    /// the dimension of the truncated ring
+    pub fn len(&self) -> usize {
+        self.monomials.len() << self.n
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// the j-th basis element
+    pub fn basis_element(&self, j: usize) -> BasisElement<M> {
+        let [v1, v2, v3, v4] = self.monomials[j % self.monomials.len()];
+        BasisElement {
+            v1,
+            v2,
+            v3,
+            v4,
+            zs: M::from_u128((j / self.monomials.len()).try_into().unwrap()).unwrap(),
+        }
+    }
+
+    /// the number of the basis element
+    pub fn number(&self, m: &BasisElement<M>) -> usize {
+        let mask = usize::try_from(m.zs.to_u128()).unwrap();
+        mask * self.monomials.len() + self.numbers[&[m.v1, m.v2, m.v3, m.v4]]
+    }
+
+    /// the coordinates of an element of the ring
+    pub fn vector(&self, x: &RingElement<'_, M, C>) -> Vector<C> {
+        let mut result = x
+            .terms()
+            .into_iter()
+            .map(|(m, c)| (self.number(&m), c))
+            .collect::<Vector<C>>();
+        result.sort_unstable_by_key(|(j, _)| *j);
+        result
+    }
+
+    /// the terms of the element with the given coordinates, in ascending order
+    pub fn terms(&self, x: &Vector<C>) -> SortedElement<M, C> {
+        let mut result = x
+            .iter()
+            .map(|(j, c)| (self.basis_element(*j), c.clone()))
+            .collect::<SortedElement<M, C>>();
+        result.sort_unstable_by_key(|(m, _)| *m);
+        result
+    }
+
+    /// The product x*y. For every term of x, the multiplications by its z's are applied to y one
+    /// after the other, so it is best if x has few terms.
+    pub fn multiply(&self, x: &Vector<C>, y: &Vector<C>) -> Vector<C> {
+        let mut accumulator = Accumulator::new(self.len());
+        // z_{…}*y for the masks of the terms of x and the masks contained in them, summed up in a
+        // separate accumulator
+        let mut products = HashMap::from([(0, y.clone())]);
+        let mut scratch = Accumulator::new(self.len());
+        for (j, c) in x {
+            let mask = j / self.monomials.len();
+            let e = j % self.monomials.len();
+            let product = self.product(mask, &mut products, &mut scratch);
+            for (i, d) in product {
+                let Some(f) = self.products[e][i % self.monomials.len()] else {
+                    continue;
+                };
+                accumulator.add_product(i - i % self.monomials.len() + f, c, d, self.t);
+            }
+        }
+        accumulator.take()
+    }
+
+    // z_{…}*y for the mask, where products contains z_{…}*y for some masks, including 0 for y
+    fn product<'b>(
+        &self,
+        mask: usize,
+        products: &'b mut HashMap<usize, Vector<C>>,
+        accumulator: &mut Accumulator<C>,
+    ) -> &'b Vector<C> {
+        if !products.contains_key(&mask) {
+            let rest = mask & (mask - 1);
+            self.product(rest, products, accumulator);
+            let k = mask.trailing_zeros() as usize;
+            for (j, c) in &products[&rest] {
+                for (i, d) in &self.z[k][*j] {
+                    accumulator.add_product(*i, c, d, self.t);
+                }
+            }
+            products.insert(mask, accumulator.take());
+        }
+        &products[&mask]
+    }
+}
+
+// a dense vector remembering its nonzero coordinates, for summing up sparse vectors
+struct Accumulator<C> {
+    values: Vec<C>,
+    touched: Vec<usize>,
+}
+
+impl<C: Coefficient> Accumulator<C> {
+    fn new(len: usize) -> Self {
+        Accumulator {
+            values: vec![C::ZERO; len],
+            touched: Vec::new(),
+        }
+    }
+
+    // self[i] += a*b
+    fn add_product(&mut self, i: usize, a: &C, b: &C, t: u8) {
+        if self.values[i] == C::ZERO {
+            self.touched.push(i);
+        }
+        self.values[i].add_product(a, b, t);
+    }
+
+    // the sum, leaving zero behind
+    fn take(&mut self) -> Vector<C> {
+        self.touched.sort_unstable();
+        self.touched.dedup();
+        let result = self
+            .touched
+            .drain(..)
+            .map(|i| (i, std::mem::replace(&mut self.values[i], C::ZERO)))
+            .filter(|(_, c)| *c != C::ZERO)
+            .collect();
+        result
+    }
+}
@@ -0,0 +1,265 @@
+// SPDX-FileCopyrightText: Alois Wohlschlager <wohlschlager@math.lmu.de>
+// SPDX-License-Identifier: EUPL-1.2
+
+//! The text output, `u^{d1}c_{…}^*≡…` printed using the `Display` implementation of
+//! `BasisElement`, and its parsing, so that old results can be analysed again without recomputing.
+//! The summary of the torsion analysis at the end is skipped.
+
+use crate::{BasisElement, Coefficient, Element, Exponent, SortedElement, ZMask};
+
+/// the rational element u^{d1}c_{cs}^* is keyed by (d1, cs)
+pub type Key = (u16, Vec<u8>);
+
+/// the rational elements in the order of the output
+pub type Rational<M, C> = Vec<(Key, Element<M, C>)>;
+
+/// the name `u^{d1}c_{…}^*` of the rational element u^{d1}c_{cs}^*
+pub fn name(d1: u16, cs: &[u8]) -> String {
+    format!(
+        "u^{{{d1}}}c_{{{}}}^*",
+        cs.iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(","),
+    )
+}
+
+/// the line of the text output for the rational element u^{d1}c_{cs}^*
+pub fn line<M: ZMask, C: Coefficient>(d1: u16, cs: &[u8], x: &SortedElement<M, C>) -> String {
+    format!("{}≡{}", name(d1, cs), terms(x))
+}
+
+/// the sum of the terms, empty for zero
+pub fn terms<M: ZMask, C: Coefficient>(x: &SortedElement<M, C>) -> String {
+    let mut result = String::new();
+    for (m, c) in x {
+        let c = c.to_string();
+        if !result.is_empty() && !c.starts_with('-') {
+            result.push('+');
+        }
+        result += &format!("{c}{m}");
+    }
+    result
+}
+
+pub fn parse<M: ZMask, C: Coefficient>(s: &str, n: u8, t: u8) -> Result<Rational<M, C>, String> {
+    let mut result = Vec::new();
+    let mut seen = std::collections::HashSet::new();
+    for (i, line) in s.lines().enumerate() {
+        if line.starts_with("torsion in degree ") || line == "no torsion found" {
+            continue;
+        }
+        let error = |e: &str| format!("line {}: {e}", i + 1);
+        let (key, x) = parse_line(line, n, t).map_err(|e| error(&e))?;
+        if !seen.insert(key.clone()) {
+            return Err(error("duplicate rational element"));
+        }
+        result.push((key, x));
+    }
+    Ok(result)
+}
+
+fn parse_line<M: ZMask, C: Coefficient>(
+    line: &str,
+    n: u8,
+    t: u8,
+) -> Result<(Key, Element<M, C>), String> {
+    let invalid = || format!("invalid line {line:?}");
+    let (d1, cs) = key(line)?;
+    if cs.last().is_some_and(|i| *i > n) {
+        return Err(invalid());
+    }
+    let rhs = line.split_once('≡').ok_or_else(invalid)?.1;
+
+    let mut x = Element::default();
+    let mut rest = rhs;
+    while !rest.is_empty() {
+        // every term but the first one starts with its sign, where `+` is omitted before `-`
+        let end = rest
+            .char_indices()
+            .skip(1)
+            .find(|(_, c)| matches!(c, '+' | '-'))
+            .map_or(rest.len(), |(i, _)| i);
+        let (term, next) = rest.split_at(end);
+        rest = match next.strip_prefix('+') {
+            Some(next) if next.is_empty() || next.starts_with('-') => return Err(invalid()),
+            Some(next) => next,
+            None => next,
+        };
+        let (m, c) = parse_term(term, n).ok_or_else(|| format!("invalid term {term:?}"))?;
+        // only the coefficients as printed, such as the residues modulo 2^t
+        let coefficient = C::from_i128(c, t);
+        if c == 0 || coefficient.to_string() != c.to_string() || x.insert(m, coefficient).is_some()
+        {
+            return Err(format!("invalid term {term:?}"));
+        }
+    }
+    Ok(((d1, cs), x))
+}
+
+/// the key of a line `u^{d1}c_{…}^*≡…`
+pub fn key(line: &str) -> Result<Key, String> {
+    let invalid = || format!("invalid line {line:?}");
+    let (lhs, _) = line.split_once('≡').ok_or_else(invalid)?;
+    let lhs = lhs.strip_prefix("u^{").ok_or_else(invalid)?;
+    let (d1, lhs) = lhs.split_once("}c_{").ok_or_else(invalid)?;
+    let cs = lhs.strip_suffix("}^*").ok_or_else(invalid)?;
+    let d1 = d1.parse().map_err(|_| invalid())?;
+    let cs = list(cs)
+        .filter(|cs| cs.iter().all(|i| (2..=128).contains(i)) && cs.is_sorted_by(|i, j| i < j))
+        .ok_or_else(invalid)?;
+    Ok((d1, cs))
+}
+
+// the term `cv_1^e1…v_4^e4z_{…}`, where factors v_i^0 are omitted and v_i^1 is written v_i
+fn parse_term<M: ZMask>(term: &str, n: u8) -> Option<(BasisElement<M>, i128)> {
+    let sign = usize::from(term.starts_with('-'));
+    let digits = sign + term[sign..].find(|c: char| !c.is_ascii_digit())?;
+    let c = term[..digits].parse().ok()?;
+    let mut rest = &term[digits..];
+    let mut e = [0; 4];
+    while let Some(factor) = rest.strip_prefix("v_") {
+        let i = usize::from(factor.as_bytes().first()?.checked_sub(b'1')?);
+        if i >= 4 || e[i] != 0 {
+            return None;
+        }
+        rest = &factor[1..];
+        e[i] = match rest.strip_prefix('^') {
+            Some(exponent) => {
+                let digits = exponent.find(|c: char| !c.is_ascii_digit())?;
+                rest = &exponent[digits..];
+                exponent[..digits]
+                    .parse::<Exponent>()
+                    .ok()
+                    .filter(|e| *e >= 2)?
+            }
+            None => 1,
+        };
+    }
+    let zs = list(rest.strip_prefix("z_{")?.strip_suffix('}')?)?;
+    if !zs.iter().all(|i| (1..=n).contains(i)) || !zs.is_sorted_by(|i, j| i < j) {
+        return None;
+    }
+    Some((
+        BasisElement {
+            v1: e[0],
+            v2: e[1],
+            v3: e[2],
+            v4: e[3],
+            zs: zs.into_iter().map(M::z).fold(M::ZERO, |accum, z| accum | z),
+        },
+        c,
+    ))
+}
+
+// a comma-separated list of indices, possibly empty
+fn list(s: &str) -> Option<Vec<u8>> {
+    if s.is_empty() {
+        return Some(Vec::new());
+    }
+    s.split(',').map(|i| i.parse().ok()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coefficients::Integer;
+
+    fn element(terms: &[(i32, [Exponent; 4], u32)]) -> SortedElement<u32, i32> {
+        terms
+            .iter()
+            .map(|&(c, [v1, v2, v3, v4], zs)| (BasisElement { v1, v2, v3, v4, zs }, c))
+            .collect()
+    }
+
+    #[test]
+    fn round_trip() {
+        let elements = [
+            (0, vec![2, 4], element(&[(1, [0; 4], 0b1010)])),
+            (
+                3,
+                vec![3],
+                element(&[(5, [1, 0, 0, 0], 0b1), (2, [2, 1, 0, 0], 0b110)]),
+            ),
+            (1, Vec::new(), element(&[])),
+        ];
+        let s = elements
+            .iter()
+            .map(|(d1, cs, x)| line(*d1, cs, x) + "\n")
+            .collect::<String>();
+        assert_eq!(
+            s.lines().nth(1).unwrap(),
+            "u^{3}c_{3}^*≡5v_1z_{1}+2v_1^2v_2z_{2,3}",
+        );
+        let parsed = parse::<u32, i32>(&s, 4, 3).unwrap();
+        assert_eq!(parsed.len(), elements.len());
+        for ((key, y), (d1, cs, x)) in parsed.into_iter().zip(elements) {
+            assert_eq!(key, (d1, cs));
+            assert!(y == x.into_iter().collect());
+        }
+    }
+
+    #[test]
+    fn negative_coefficients() {
+        let x = vec![
+            (
+                BasisElement {
+                    v1: 0,
+                    v2: 0,
+                    v3: 0,
+                    v4: 0,
+                    zs: 0b1,
+                },
+                Integer::from(-3),
+            ),
+            (
+                BasisElement {
+                    v1: 1,
+                    v2: 0,
+                    v3: 0,
+                    v4: 0,
+                    zs: 0b10,
+                },
+                Integer::from(5),
+            ),
+            (
+                BasisElement {
+                    v1: 2,
+                    v2: 0,
+                    v3: 0,
+                    v4: 0,
+                    zs: 0b100,
+                },
+                Integer::from(-1),
+            ),
+        ];
+        let s = line(2, &[3], &x);
+        assert_eq!(s, "u^{2}c_{3}^*≡-3z_{1}+5v_1z_{2}-1v_1^2z_{3}");
+        let parsed = parse::<u32, Integer>(&s, 4, 3).unwrap();
+        assert!(parsed == vec![((2, vec![3]), x.into_iter().collect())]);
+        // modulo 2^t, only the residues are printed
+        for s in [&s, "u^{2}c_{3}^*≡-3z_{1}"] {
+            assert!(parse::<u32, i32>(s, 4, 3).is_err(), "{s:?}");
+        }
+    }
+
+    #[test]
+    fn invalid_lines() {
+        for s in [
+            "u^{0}c_{}^*≡8z_{1}",
+            "u^{0}c_{}^*≡0z_{1}",
+            "u^{0}c_{}^*≡1z_{5}",
+            "u^{0}c_{}^*≡1z_{1}+1z_{1}",
+            "u^{0}c_{}^*≡1v_1^1z_{1}",
+            "u^{0}c_{1}^*≡1z_{1}",
+            "u^{0}c_{}^*≡1z_{1}\nu^{0}c_{}^*≡1z_{2}",
+            "u^{0}c_{}^*≡1z_{1}+-1z_{2}",
+            "u^{0}c_{}^*≡1z_{1}+",
+            "u^{0}c_{}^*≡+1z_{1}",
+            "u^{0}c_{}^*≡1z_{1}--1z_{2}",
+            "u^{0}c_{}^*≡≡1z_{1}",
+        ] {
+            assert!(parse::<u32, i32>(s, 4, 3).is_err(), "{s:?}");
+        }
+    }
+}
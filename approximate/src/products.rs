@@ -0,0 +1,312 @@
+// SPDX-FileCopyrightText: Alois Wohlschlager <wohlschlager@math.lmu.de>
+// SPDX-License-Identifier: EUPL-1.2
+
+//! Persistent multiplication tables. The products m*z_k in the cache only depend on n, t, the
+//! truncation and the formula tables, so they can be written to a file after a run and loaded
+//! again by later runs with the same parameters, whatever else they compute.
+//!
+//! The files are binary, with all integers in little endian. After the magic bytes and the
+//! version, the parameters follow: n, t, whether the computation is in connective K-theory, the
+//! truncation order, the number of further generators of the ideal and their exponents, one byte
+//! each, and the checksum of the formula tables (8 bytes). Then the number of entries (8 bytes) is
+//! followed by the entries, each consisting of k, the basis element m, the number of terms of
+//! m*z_k (4 bytes) and the terms. Basis elements are stored as the exponents of v1, …, v4, one
+//! byte each, and the mask of the z's (16 bytes), and terms as the basis element followed by the
+//! coefficient modulo 2^t (8 bytes).
+
+use crate::{truncation::Truncation, BasisElement, Cache, Coefficient, Element, ZMask};
+use std::{
+    fmt::{Display, Formatter},
+    io::{BufWriter, ErrorKind, Write},
+};
+
+const MAGIC: &[u8; 8] = b"OGRPROD\0";
+/// the version of the file format, to be increased on any incompatible change
+pub const VERSION: u32 = 1;
+
+/// the parameters on which the products depend
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Parameters {
+    pub n: u8,
+    pub t: u8,
+    pub connective: bool,
+    pub truncation: Truncation,
+    /// the checksum of the formula tables, see [`crate::table::Table::checksum`]
+    pub checksum: u64,
+}
+
+impl Parameters {
+    fn write(&self, out: &mut Vec<u8>) {
+        out.extend([
+            self.n,
+            self.t,
+            self.connective.into(),
+            self.truncation.order,
+            u8::try_from(self.truncation.generators.len()).unwrap(),
+        ]);
+        for g in &self.truncation.generators {
+            out.extend(g);
+        }
+        out.extend(self.checksum.to_le_bytes());
+    }
+
+    fn read(reader: &mut Reader<'_>) -> Option<Self> {
+        let [n, t, connective, order, generators] = reader.bytes()?;
+        Some(Parameters {
+            n,
+            t,
+            connective: match connective {
+                0 => false,
+                1 => true,
+                _ => return None,
+            },
+            truncation: Truncation {
+                order,
+                generators: (0..generators)
+                    .map(|_| reader.bytes())
+                    .collect::<Option<_>>()?,
+            },
+            checksum: u64::from_le_bytes(reader.bytes()?),
+        })
+    }
+}
+
+impl Display for Parameters {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "n={}, t={}, order {}",
+            self.n, self.t, self.truncation.order
+        )?;
+        if !self.truncation.generators.is_empty() {
+            write!(f, ", ideal {}", self.truncation)?;
+        }
+        if self.connective {
+            write!(f, ", connective")?;
+        }
+        write!(f, ", formula tables {:016x}", self.checksum)
+    }
+}
+
+/// Fill the cache from the file, if it exists. A file for different parameters or of a different
+/// version is rejected.
+pub fn load<M: ZMask, C: Coefficient>(
+    path: &str,
+    parameters: &Parameters,
+    cache: &Cache<M, C>,
+) -> Result<(), String> {
+    let data = match std::fs::read(path) {
+        Ok(data) => data,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(format!("cannot read {path}: {e}")),
+    };
+    let invalid = || format!("invalid multiplication table {path}");
+    let mut reader = Reader(&data);
+    if reader.bytes() != Some(*MAGIC) {
+        return Err(format!("{path} is not a multiplication table"));
+    }
+    let version = u32::from_le_bytes(reader.bytes().ok_or_else(invalid)?);
+    if version != VERSION {
+        return Err(format!(
+            "the multiplication table {path} has version {version}, but only version {VERSION} is \
+             supported",
+        ));
+    }
+    let stored = Parameters::read(&mut reader).ok_or_else(invalid)?;
+    if stored != *parameters {
+        return Err(format!(
+            "the multiplication table {path} belongs to a different computation ({stored} \
+             instead of {parameters})",
+        ));
+    }
+    let entries = u64::from_le_bytes(reader.bytes().ok_or_else(invalid)?);
+    for _ in 0..entries {
+        let [k] = reader.bytes().ok_or_else(invalid)?;
+        let m = reader.basis_element().ok_or_else(invalid)?;
+        let terms = u32::from_le_bytes(reader.bytes().ok_or_else(invalid)?);
+        let x = (0..terms)
+            .map(|_| {
+                let m = reader.basis_element()?;
+                let c = u64::from_le_bytes(reader.bytes()?);
+                Some((m, C::from_i128(c.into(), parameters.t)))
+            })
+            .collect::<Option<Element<M, C>>>()
+            .ok_or_else(invalid)?;
+        cache.insert((m, k), x);
+    }
+    if !reader.0.is_empty() {
+        return Err(invalid());
+    }
+    Ok(())
+}
+
+/// Write the products in the cache to the file. It is written to a temporary file first, so that
+/// it is never incomplete.
+pub fn save<M: ZMask, C: Coefficient>(
+    path: &str,
+    parameters: &Parameters,
+    cache: &Cache<M, C>,
+) -> Result<(), String> {
+    let mut header = MAGIC.to_vec();
+    header.extend(VERSION.to_le_bytes());
+    parameters.write(&mut header);
+    let shards = cache
+        .shards
+        .iter()
+        .map(|shard| shard.lock().unwrap())
+        .collect::<Vec<_>>();
+    let entries = shards
+        .iter()
+        .map(|shard| shard.entries.len())
+        .sum::<usize>();
+    header.extend(u64::try_from(entries).unwrap().to_le_bytes());
+
+    let temporary = format!("{path}.tmp");
+    let error = |e: std::io::Error| format!("cannot write {path}: {e}");
+    let mut writer = BufWriter::new(std::fs::File::create(&temporary).map_err(error)?);
+    writer.write_all(&header).map_err(error)?;
+    for shard in &shards {
+        for ((m, k), entry) in &shard.entries {
+            let mut s = vec![*k];
+            write_basis_element(&mut s, m);
+            s.extend(u32::try_from(entry.value.len()).unwrap().to_le_bytes());
+            for (m, c) in &entry.value {
+                write_basis_element(&mut s, m);
+                s.extend(c.residue(parameters.t).to_le_bytes());
+            }
+            writer.write_all(&s).map_err(error)?;
+        }
+    }
+    writer
+        .into_inner()
+        .map_err(|e| e.into_error())
+        .and_then(|file| file.sync_all())
+        .and_then(|()| std::fs::rename(&temporary, path))
+        .map_err(error)
+}
+
+fn write_basis_element<M: ZMask>(out: &mut Vec<u8>, m: &BasisElement<M>) {
+    out.extend([m.v1, m.v2, m.v3, m.v4]);
+    out.extend(m.zs.to_u128().to_le_bytes());
+}
+
+// the data not read yet
+struct Reader<'a>(&'a [u8]);
+
+impl Reader<'_> {
+    fn bytes<const N: usize>(&mut self) -> Option<[u8; N]> {
+        let (bytes, rest) = self.0.split_first_chunk()?;
+        self.0 = rest;
+        Some(*bytes)
+    }
+
+    fn basis_element<M: ZMask>(&mut self) -> Option<BasisElement<M>> {
+        let [v1, v2, v3, v4] = self.bytes()?;
+        let zs = M::from_u128(u128::from_le_bytes(self.bytes()?))?;
+        Some(BasisElement { v1, v2, v3, v4, zs })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ring::Ring, table::Table, temporary_path, DEFAULT_TABLE};
+
+    type Entries = Vec<((BasisElement<u32>, u8), Vec<(BasisElement<u32>, i32)>)>;
+
+    fn parameters() -> Parameters {
+        Parameters {
+            n: 5,
+            t: 3,
+            connective: false,
+            truncation: Truncation::parse(4, "v1^3").unwrap(),
+            checksum: Table::parse(DEFAULT_TABLE).unwrap().checksum,
+        }
+    }
+
+    // the products needed for a few rational elements
+    fn ring() -> Ring<u32, i32> {
+        let Parameters {
+            n, t, truncation, ..
+        } = parameters();
+        let ring = Ring::new(&Table::parse(DEFAULT_TABLE).unwrap(), n, t, truncation);
+        ring.rational_element(3, &[2, 4]);
+        ring.rational_element(5, &[]);
+        ring
+    }
+
+    fn entries(cache: &Cache<u32, i32>) -> Entries {
+        let mut result = cache
+            .shards
+            .iter()
+            .flat_map(|shard| {
+                shard
+                    .lock()
+                    .unwrap()
+                    .entries
+                    .iter()
+                    .map(|(key, entry)| {
+                        let mut value = entry.value.clone().into_iter().collect::<Vec<_>>();
+                        value.sort();
+                        (*key, value)
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        result.sort();
+        result
+    }
+
+    #[test]
+    fn round_trip() {
+        let path = temporary_path("round-trip.products");
+        // a missing file is not an error
+        let cache = Cache::new();
+        load::<u32, i32>(&path, &parameters(), &cache).unwrap();
+        assert!(entries(&cache).is_empty());
+
+        let ring = ring();
+        assert!(!entries(ring.cache()).is_empty());
+        save(&path, &parameters(), ring.cache()).unwrap();
+        load(&path, &parameters(), &cache).unwrap();
+        assert!(entries(&cache) == entries(ring.cache()));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn invalid_files() {
+        let path = temporary_path("invalid.products");
+        save(&path, &parameters(), ring().cache()).unwrap();
+        let data = std::fs::read(&path).unwrap();
+        let load = |data: &[u8], parameters: &Parameters| {
+            std::fs::write(&path, data).unwrap();
+            load::<u32, i32>(&path, parameters, &Cache::new())
+        };
+        assert!(load(&data, &parameters()).is_ok());
+
+        let mut other = parameters();
+        other.checksum ^= 1;
+        let e = load(&data, &other).unwrap_err();
+        assert!(e.contains("belongs to a different computation"), "{e}");
+        other = parameters();
+        other.truncation = Truncation::order(4);
+        assert!(load(&data, &other).is_err());
+
+        let mut version = data.clone();
+        version[8] += 1;
+        let e = load(&version, &parameters()).unwrap_err();
+        assert!(e.contains("has version 2"), "{e}");
+        let mut magic = data.clone();
+        magic[0] = b'X';
+        assert!(load(&magic, &parameters()).is_err());
+
+        // truncated files and trailing garbage
+        for length in [0, 4, 10, 30, data.len() / 2, data.len() - 1] {
+            assert!(load(&data[..length], &parameters()).is_err(), "{length}");
+        }
+        let mut longer = data.clone();
+        longer.push(0);
+        assert!(load(&longer, &parameters()).is_err());
+        std::fs::remove_file(path).unwrap();
+    }
+}
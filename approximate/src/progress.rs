@@ -0,0 +1,134 @@
+// SPDX-FileCopyrightText: Alois Wohlschlager <wohlschlager@math.lmu.de>
+// SPDX-License-Identifier: EUPL-1.2
+
+//! Progress reports for long runs on stderr. The ETA is based on the estimated cost of the masks
+//! from [`generate_exact::progress::Costs`], as the masks with few c_i^* need many more
+//! multiplications by u, in degrees with many more terms, than those with many.
+
+use crate::{chern_classes, shard::Shard, Cache, Coefficient, ZMask};
+use generate_exact::progress::Costs;
+use std::time::{Duration, Instant};
+
+// the minimal time between two reports
+const INTERVAL: Duration = Duration::from_secs(10);
+
+pub struct Progress {
+    n: u8,
+    costs: Costs,
+    // tail[k] is the cost of the multiplications by u with results in the degrees from k on,
+    // without any c_i^*
+    tail: Vec<f64>,
+    start: Instant,
+    last: Instant,
+    // the number of masks of the run and of those done, including the ones from a checkpoint
+    masks: u128,
+    masks_done: u128,
+    results: usize,
+    // the estimated cost of the masks computed in this run, and of those done
+    cost: f64,
+    cost_done: f64,
+}
+
+impl Progress {
+    /// the progress of computing the masks of the shard, where those from `end` on are already
+    /// known
+    pub fn new(n: u8, order: u8, shard: Shard, end: u128) -> Self {
+        let d = u32::from(n) * u32::from(n + 1) / 2;
+        let costs = Costs::new(n.into(), Some(order.into()));
+        let mut tail = vec![0.0; usize::try_from(d + 2).unwrap()];
+        for k in (0..=d).rev() {
+            let k = usize::try_from(k).unwrap();
+            tail[k] = tail[k + 1] + costs.step(k.try_into().unwrap(), 0);
+        }
+        let all = 1 << (n - 1);
+        let mut progress = Progress {
+            n,
+            costs,
+            tail,
+            start: Instant::now(),
+            last: Instant::now(),
+            masks: 0,
+            masks_done: 0,
+            results: 0,
+            cost: 0.0,
+            cost_done: 0.0,
+        };
+        let mut next = shard.previous(all);
+        while let Some(mask) = next {
+            progress.masks += 1;
+            if mask >= end {
+                progress.masks_done += 1;
+            } else {
+                progress.cost += progress.mask(mask);
+            }
+            next = shard.previous(mask);
+        }
+        progress
+    }
+
+    // one multiplication by c_i^* and all the multiplications by u
+    fn mask(&self, mask: u128) -> f64 {
+        let cs = chern_classes(mask, self.n);
+        let degree = cs.iter().copied().map(u32::from).sum::<u32>();
+        let dual = if mask == 0 {
+            0.0
+        } else {
+            self.costs.step(degree, cs.len())
+        };
+        dual + self.tail[usize::try_from(degree + 1).unwrap()]
+            / 2_f64.powi(cs.len().try_into().unwrap())
+    }
+
+    /// Record that the mask is done, giving the number of results, and report the progress if
+    /// enough time has passed since the last report.
+    pub fn record<M: ZMask, C: Coefficient>(
+        &mut self,
+        mask: u128,
+        results: usize,
+        cache: &Cache<M, C>,
+    ) {
+        self.masks_done += 1;
+        self.results += results;
+        self.cost_done += self.mask(mask);
+        if self.last.elapsed() >= INTERVAL || self.masks_done == self.masks {
+            self.report(cache);
+            self.last = Instant::now();
+        }
+    }
+
+    fn report<M: ZMask, C: Coefficient>(&self, cache: &Cache<M, C>) {
+        let statistics = cache.statistics();
+        let elapsed = self.start.elapsed();
+        let eta = if self.cost_done == 0.0 {
+            "unknown".to_owned()
+        } else {
+            let remaining = elapsed.as_secs_f64() * (self.cost - self.cost_done) / self.cost_done;
+            format_duration(Duration::from_secs_f64(remaining.max(0.0)))
+        };
+        eprintln!(
+            "progress: {} of {} masks ({:.1}% of the estimated cost), {} rational elements, cache \
+             of {} entries ({:.1} MiB), {} elapsed, ETA {eta}",
+            self.masks_done,
+            self.masks,
+            if self.cost == 0.0 {
+                100.0
+            } else {
+                100.0 * self.cost_done / self.cost
+            },
+            self.results,
+            statistics.entries,
+            statistics.bytes as f64 / f64::from(1 << 20),
+            format_duration(elapsed),
+        );
+    }
+}
+
+// like 1h02m03s
+fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    match (seconds / 3600, seconds / 60 % 60, seconds % 60) {
+        (0, 0, s) => format!("{s}s"),
+        (0, m, s) => format!("{m}m{s:02}s"),
+        (h, m, s) => format!("{h}h{m:02}m{s:02}s"),
+    }
+}
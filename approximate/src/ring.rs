@@ -0,0 +1,423 @@
+// SPDX-FileCopyrightText: Alois Wohlschlager <wohlschlager@math.lmu.de>
+// SPDX-License-Identifier: EUPL-1.2
+
+//! The truncated cohomology ring as an object owning the relations and the multiplication cache,
+//! so that computations with its elements can be written using the usual operators. All results
+//! are reduced modulo the truncation ideal in the v's, and for the integers modulo 2^t as
+//! coefficients also modulo 2^t.
+
+use crate::{
+    multiply_element_element, output, rational_element, table::Table, term, truncation::Truncation,
+    BasisElement, Cache, Coefficient, Element, Relations, SortedElement, ZMask,
+};
+use std::{
+    fmt::{Display, Formatter},
+    ops::{Add, Mul, Neg, Sub},
+};
+
+/// BP^*(OGr(n)) with coefficients in C modulo the ideal `truncation` in the v's, and also modulo
+/// v2, v3 and v4 for the tables of connective K-theory
+pub struct Ring<M, C> {
+    pub n: u8,
+    pub t: u8,
+    pub truncation: Truncation,
+    pub connective: bool,
+    relations: Relations<M, C>,
+    cache: Cache<M, C>,
+}
+
+/// An element of a [`Ring`]. Elements of different rings must not be mixed.
+#[derive(Clone)]
+pub struct RingElement<'a, M, C> {
+    ring: &'a Ring<M, C>,
+    terms: Element<M, C>,
+}
+
+impl<M: ZMask, C: Coefficient> Ring<M, C> {
+    /// the ring with the relations from the given formula tables
+    pub fn new(table: &Table, n: u8, t: u8, truncation: Truncation) -> Self {
+        assert!(
+            truncation.order <= table.order,
+            "the formula tables are only available up to degree {} in the v's",
+            table.order,
+        );
+        Ring {
+            n,
+            t,
+            connective: table.connective,
+            relations: Relations::new(table, n, t, &truncation),
+            truncation,
+            cache: Cache::new(),
+        }
+    }
+
+    /// the same ring using the given cache, for example one with a memory budget
+    pub fn with_cache(self, cache: Cache<M, C>) -> Self {
+        Ring { cache, ..self }
+    }
+
+    /// the products m*z_k computed so far
+    pub fn cache(&self) -> &Cache<M, C> {
+        &self.cache
+    }
+
+    /// the element with the given terms
+    pub fn element(
+        &self,
+        terms: impl IntoIterator<Item = (BasisElement<M>, C)>,
+    ) -> RingElement<'_, M, C> {
+        let mut result = Element::default();
+        for (m, c) in terms {
+            assert!(m.zs == m.zs & M::first(self.n), "z_i for i > n in {m}");
+            if self.truncation.drops(&m) || (self.connective && (m.v2, m.v3, m.v4) != (0, 0, 0)) {
+                continue;
+            }
+            result
+                .entry(m)
+                .or_insert(C::ZERO)
+                .add_product(&c, &C::ONE, self.t);
+        }
+        result.retain(|_, c| *c != C::ZERO);
+        RingElement {
+            ring: self,
+            terms: result,
+        }
+    }
+
+    /// the integer c
+    pub fn constant(&self, c: i128) -> RingElement<'_, M, C> {
+        self.element(term(C::from_i128(c, self.t), [0; 4], &[], self.n))
+    }
+
+    pub fn zero(&self) -> RingElement<'_, M, C> {
+        self.constant(0)
+    }
+
+    pub fn one(&self) -> RingElement<'_, M, C> {
+        self.constant(1)
+    }
+
+    /// v_i for i = 1, …, 4
+    pub fn v(&self, i: u8) -> RingElement<'_, M, C> {
+        assert!((1..=4).contains(&i), "only v_1, …, v_4 are available");
+        let mut e = [0; 4];
+        e[usize::from(i - 1)] = 1;
+        self.element(term(C::ONE, e, &[], self.n))
+    }
+
+    /// z_i for i = 1, …, n
+    pub fn z(&self, i: u8) -> RingElement<'_, M, C> {
+        assert!(
+            (1..=self.n).contains(&i),
+            "z_{i} does not exist for n = {}",
+            self.n
+        );
+        self.element(term(C::ONE, [0; 4], &[i.into()], self.n))
+    }
+
+    pub fn u(&self) -> RingElement<'_, M, C> {
+        self.wrap(self.relations.u.clone())
+    }
+
+    /// c_i^* for i = 1, …, n
+    pub fn dual(&self, i: u8) -> RingElement<'_, M, C> {
+        assert!(
+            (1..=self.n).contains(&i),
+            "c_{i}^* does not exist for n = {}",
+            self.n
+        );
+        self.wrap(self.relations.duals[&i].clone())
+    }
+
+    /// the rational element u^k*c_{cs}^*, computed in the same way as by `approximate`
+    pub fn rational_element(&self, k: u16, cs: &[u8]) -> RingElement<'_, M, C> {
+        self.wrap(rational_element(
+            k,
+            cs,
+            self.n,
+            self.t,
+            &self.truncation,
+            &self.relations,
+            &self.cache,
+        ))
+    }
+
+    // an element which is already reduced
+    fn wrap(&self, terms: Element<M, C>) -> RingElement<'_, M, C> {
+        RingElement { ring: self, terms }
+    }
+}
+
+impl<'a, M: ZMask, C: Coefficient> RingElement<'a, M, C> {
+    pub fn ring(&self) -> &'a Ring<M, C> {
+        self.ring
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.terms.is_empty()
+    }
+
+    /// the coefficient of the given basis element
+    pub fn coefficient(&self, m: &BasisElement<M>) -> C {
+        self.terms.get(m).cloned().unwrap_or(C::ZERO)
+    }
+
+    /// the terms in ascending order
+    pub fn terms(&self) -> SortedElement<M, C> {
+        let mut result = self
+            .terms
+            .iter()
+            .map(|(m, c)| (*m, c.clone()))
+            .collect::<Vec<_>>();
+        result.sort_unstable_by_key(|(m, _)| *m);
+        result
+    }
+
+    pub fn into_terms(self) -> Element<M, C> {
+        self.terms
+    }
+
+    /// the product with the integer c, also available as `self * c`
+    pub fn scale(&self, c: i128) -> Self {
+        let c = C::from_i128(c, self.ring.t);
+        self.ring.element(self.terms.iter().map(|(m, c2)| {
+            let mut product = C::ZERO;
+            product.add_product(&c, c2, self.ring.t);
+            (*m, product)
+        }))
+    }
+
+    pub fn pow(&self, k: u32) -> Self {
+        (0..k).fold(self.ring.one(), |accum, _| &accum * self)
+    }
+
+    fn check(&self, other: &Self) {
+        assert!(
+            std::ptr::eq(self.ring, other.ring),
+            "elements of different rings cannot be combined",
+        );
+    }
+}
+
+impl<M: ZMask, C: Coefficient> PartialEq for RingElement<'_, M, C> {
+    fn eq(&self, other: &Self) -> bool {
+        self.check(other);
+        self.terms == other.terms
+    }
+}
+
+impl<M: ZMask, C: Coefficient> Eq for RingElement<'_, M, C> {}
+
+impl<'a, M: ZMask, C: Coefficient> Add for &RingElement<'a, M, C> {
+    type Output = RingElement<'a, M, C>;
+
+    fn add(self, other: Self) -> RingElement<'a, M, C> {
+        self.check(other);
+        self.ring.element(
+            self.terms
+                .iter()
+                .chain(&other.terms)
+                .map(|(m, c)| (*m, c.clone())),
+        )
+    }
+}
+
+impl<'a, M: ZMask, C: Coefficient> Neg for &RingElement<'a, M, C> {
+    type Output = RingElement<'a, M, C>;
+
+    fn neg(self) -> RingElement<'a, M, C> {
+        self.ring
+            .element(self.terms.iter().map(|(m, c)| (*m, c.neg(self.ring.t))))
+    }
+}
+
+impl<'a, M: ZMask, C: Coefficient> Sub for &RingElement<'a, M, C> {
+    type Output = RingElement<'a, M, C>;
+
+    fn sub(self, other: Self) -> RingElement<'a, M, C> {
+        self + &-other
+    }
+}
+
+impl<'a, M: ZMask, C: Coefficient> Mul for &RingElement<'a, M, C> {
+    type Output = RingElement<'a, M, C>;
+
+    fn mul(self, other: Self) -> RingElement<'a, M, C> {
+        self.check(other);
+        let ring = self.ring;
+        ring.wrap(multiply_element_element(
+            self.terms.clone(),
+            other.terms.clone(),
+            ring.t,
+            &ring.truncation,
+            &ring.relations.squares,
+            &ring.cache,
+        ))
+    }
+}
+
+// multiplication by an integer
+impl<'a, M: ZMask, C: Coefficient> Mul<i128> for &RingElement<'a, M, C> {
+    type Output = RingElement<'a, M, C>;
+
+    fn mul(self, c: i128) -> RingElement<'a, M, C> {
+        self.scale(c)
+    }
+}
+
+// the same operations on owned elements
+macro_rules! impl_owned {
+    ($($trait:ident $method:ident),*) => {$(
+        impl<'a, M: ZMask, C: Coefficient> $trait for RingElement<'a, M, C> {
+            type Output = RingElement<'a, M, C>;
+
+            fn $method(self, other: Self) -> RingElement<'a, M, C> {
+                (&self).$method(&other)
+            }
+        }
+
+        impl<'a, M: ZMask, C: Coefficient> $trait<&RingElement<'a, M, C>>
+            for RingElement<'a, M, C>
+        {
+            type Output = RingElement<'a, M, C>;
+
+            fn $method(self, other: &Self) -> RingElement<'a, M, C> {
+                (&self).$method(other)
+            }
+        }
+
+        impl<'a, M: ZMask, C: Coefficient> $trait<RingElement<'a, M, C>>
+            for &RingElement<'a, M, C>
+        {
+            type Output = RingElement<'a, M, C>;
+
+            fn $method(self, other: RingElement<'a, M, C>) -> RingElement<'a, M, C> {
+                self.$method(&other)
+            }
+        }
+    )*};
+}
+
+impl_owned!(Add add, Sub sub, Mul mul);
+
+impl<'a, M: ZMask, C: Coefficient> Neg for RingElement<'a, M, C> {
+    type Output = RingElement<'a, M, C>;
+
+    fn neg(self) -> RingElement<'a, M, C> {
+        -&self
+    }
+}
+
+impl<'a, M: ZMask, C: Coefficient> Mul<i128> for RingElement<'a, M, C> {
+    type Output = RingElement<'a, M, C>;
+
+    fn mul(self, c: i128) -> RingElement<'a, M, C> {
+        &self * c
+    }
+}
+
+// in the same form as the output of `approximate`
+impl<M: ZMask, C: Coefficient> Display for RingElement<'_, M, C> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.terms.is_empty() {
+            return write!(f, "0");
+        }
+        write!(f, "{}", output::terms(&self.terms()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{coefficients::Integer, DEFAULT_TABLE};
+
+    fn ring(n: u8, t: u8) -> Ring<u32, i32> {
+        Ring::new(
+            &Table::parse(DEFAULT_TABLE).unwrap(),
+            n,
+            t,
+            Truncation::order(4),
+        )
+    }
+
+    #[test]
+    fn operators() {
+        let ring = ring(5, 3);
+        let (z1, z2, z3, v1) = (ring.z(1), ring.z(2), ring.z(3), ring.v(1));
+        let x = &v1 * &z2 + ring.constant(3) * &z3;
+        assert!(&x + &z1 - &z1 == x);
+        assert!((&x - &x).is_zero());
+        assert!(-&x + &x == ring.zero());
+        assert!(&x * 3 == &(&x + &x) + &x);
+        assert!(x.scale(3) == &x * ring.constant(3));
+        // the coefficients are reduced modulo 2^3
+        assert!((&x * 8).is_zero());
+        assert!(&z1 * &z2 == &z2 * &z1);
+        assert!(&(&z1 * &z2) * &z3 == &z1 * &(&z2 * &z3));
+        assert!(&z1 * &(&z2 + &v1) == &z1 * &z2 + &z1 * &v1);
+        assert!(&ring.one() * &x == x);
+        // the truncation drops the monomials of degree larger than 4 in the v's
+        assert!(v1.pow(4) != ring.zero());
+        assert!(v1.pow(5).is_zero());
+        assert!(ring.v(2).pow(2).is_zero());
+    }
+
+    #[test]
+    fn powers() {
+        let ring = ring(5, 3);
+        let u = ring.u();
+        assert!(u.pow(0) == ring.one());
+        assert!(u.pow(1) == u);
+        assert!(u.pow(3) == &(&u * &u) * &u);
+        // the rational elements are the products of powers of u with the c_i^*
+        for cs in [&[][..], &[2], &[2, 4], &[3, 4, 5]] {
+            for k in 0..3 {
+                let product = cs.iter().fold(u.pow(k), |accum, i| accum * ring.dual(*i));
+                assert!(
+                    product == ring.rational_element(k.try_into().unwrap(), cs),
+                    "u^{k}c_{cs:?}^*",
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn bounded_cache() {
+        let unbounded = ring(5, 3);
+        let elements = |ring: &Ring<u32, i32>| {
+            let mut result = Vec::new();
+            for mask in 0..1 << 4 {
+                for k in 0..3 {
+                    let cs = crate::chern_classes(mask, 5);
+                    result.push(ring.rational_element(k, &cs).terms());
+                }
+            }
+            result
+        };
+        let expected = elements(&unbounded);
+        assert_eq!(unbounded.cache().statistics().evictions, 0);
+        // a budget of a quarter of the products computed without eviction
+        let budget = unbounded.cache().statistics().bytes / 4;
+        let bounded = ring(5, 3).with_cache(Cache::with_budget(budget));
+        assert!(elements(&bounded) == expected);
+        let statistics = bounded.cache().statistics();
+        assert!(statistics.evictions > 0);
+        assert!(statistics.bytes <= budget);
+    }
+
+    #[test]
+    fn display() {
+        let ring = Ring::<u32, Integer>::new(
+            &Table::parse(DEFAULT_TABLE).unwrap(),
+            3,
+            3,
+            Truncation::order(4),
+        );
+        assert_eq!(ring.zero().to_string(), "0");
+        assert_eq!(
+            (ring.z(1) - ring.v(1) * ring.z(2)).to_string(),
+            "1z_{1}-1v_1z_{2}"
+        );
+        assert_eq!((ring.z(2) * 3 - ring.z(1)).to_string(), "-1z_{1}+3z_{2}");
+    }
+}
@@ -0,0 +1,259 @@
+// SPDX-FileCopyrightText: Alois Wohlschlager <wohlschlager@math.lmu.de>
+// SPDX-License-Identifier: EUPL-1.2
+
+//! Splitting a run into shards computed independently, for example on different machines, and
+//! merging their outputs again. The i-th of N shards computes the masks congruent to i-1 modulo N,
+//! which spreads the expensive masks evenly. Its text output is the one of a complete run
+//! restricted to these masks, enclosed by a line `# shard i/N of <parameters>` and a line
+//! `# shard i/N done`, so that the merge can check that the shards belong together and are
+//! complete.
+
+use crate::output;
+use std::{
+    collections::BTreeMap,
+    fmt::{Display, Formatter},
+};
+
+/// the i-th of N shards, for 1 <= i <= N
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Shard {
+    pub i: u128,
+    pub count: u128,
+}
+
+impl Shard {
+    /// the shard containing all masks
+    pub const ALL: Shard = Shard { i: 1, count: 1 };
+
+    /// the shard given as `i/N`
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let invalid = || format!("invalid shard {s:?}");
+        let (i, count) = s.split_once('/').ok_or_else(invalid)?;
+        let i = i.parse().map_err(|_| invalid())?;
+        let count = count.parse().map_err(|_| invalid())?;
+        if !(1..=count).contains(&i) {
+            return Err(invalid());
+        }
+        Ok(Shard { i, count })
+    }
+
+    pub fn contains(&self, mask: u128) -> bool {
+        mask % self.count == self.i - 1
+    }
+
+    /// the largest mask of the shard below `end`
+    pub fn previous(&self, end: u128) -> Option<u128> {
+        let mask = end.checked_sub(1)?.checked_sub(self.i - 1)?;
+        Some(mask - mask % self.count + self.i - 1)
+    }
+
+    /// the first line of the output of the shard
+    pub fn header(&self, parameters: &str) -> String {
+        format!("# shard {self} of {parameters}")
+    }
+
+    /// the last line of the output of the shard
+    pub fn trailer(&self) -> String {
+        format!("# shard {self} done")
+    }
+}
+
+impl Display for Shard {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.i, self.count)
+    }
+}
+
+/// Merge the outputs of the shards, given together with their names, into the output of a
+/// complete run. All shards of the same run need to be present exactly once and complete.
+pub fn merge(outputs: &[(String, String)]) -> Result<String, String> {
+    let mut parameters = None;
+    let mut shards = BTreeMap::new();
+    // the lines of all shards by mask, in descending order
+    let mut blocks = BTreeMap::new();
+    for (name, s) in outputs {
+        let error = |e: String| format!("{name}: {e}");
+        let mut lines = s.lines();
+        let header = lines.next().unwrap_or_default();
+        let (shard, p) = header
+            .strip_prefix("# shard ")
+            .and_then(|header| header.split_once(" of "))
+            .ok_or_else(|| error("not the output of a shard".to_owned()))?;
+        let shard = Shard::parse(shard).map_err(error)?;
+        if *parameters.get_or_insert(p) != p {
+            return Err(error(format!(
+                "belongs to a different computation ({p} instead of {})",
+                parameters.unwrap(),
+            )));
+        }
+        if let Some(other) = shards.insert(shard.i, (shard.count, name)) {
+            return Err(error(format!("shard {shard} is also given by {}", other.1)));
+        }
+        let mut done = false;
+        for line in lines {
+            if done {
+                return Err(error("output after the end of the shard".to_owned()));
+            }
+            if line == shard.trailer() {
+                done = true;
+                continue;
+            }
+            let (_, cs) = output::key(line).map_err(error)?;
+            let mask = cs.iter().map(|i| 1 << (i - 2)).sum::<u128>();
+            if !shard.contains(mask) {
+                return Err(error(format!("{line:?} does not belong to shard {shard}")));
+            }
+            blocks
+                .entry(std::cmp::Reverse(mask))
+                .or_insert_with(Vec::new)
+                .push(line);
+        }
+        if !done {
+            return Err(error(format!("shard {shard} is incomplete")));
+        }
+    }
+    let count = shards.values().next().ok_or("no shards given")?.0;
+    if let Some((_, name)) = shards.values().find(|(c, _)| *c != count) {
+        return Err(format!(
+            "{name}: the number of shards differs from the other ones"
+        ));
+    }
+    if let Some(i) = (1..=count).find(|i| !shards.contains_key(i)) {
+        return Err(format!("shard {i}/{count} is missing"));
+    }
+    Ok(blocks
+        .into_values()
+        .flatten()
+        .map(|line| format!("{line}\n"))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PARAMETERS: &str = "n=4, t=2, order 4, formula tables 0123456789abcdef";
+
+    // the lines of a complete run for n = 4 in the order of the output, with the right-hand sides
+    // only indicating the mask
+    fn lines() -> Vec<(u128, String)> {
+        let mut result = Vec::new();
+        for mask in (0..8).rev() {
+            let cs = (2..=4)
+                .filter(|i| mask & (1 << (i - 2)) != 0)
+                .collect::<Vec<_>>();
+            for d1 in 0..=10 - cs.iter().sum::<u128>() {
+                let cs = cs.iter().map(u128::to_string).collect::<Vec<_>>();
+                result.push((
+                    mask,
+                    format!("u^{{{d1}}}c_{{{}}}^*≡{mask}v_1z_{{1}}", cs.join(",")),
+                ));
+            }
+        }
+        result
+    }
+
+    fn output(shard: Shard) -> String {
+        let mut s = shard.header(PARAMETERS) + "\n";
+        for (_, line) in lines().iter().filter(|(mask, _)| shard.contains(*mask)) {
+            s += &format!("{line}\n");
+        }
+        s + &shard.trailer() + "\n"
+    }
+
+    fn shards(count: u128) -> Vec<(String, String)> {
+        (1..=count)
+            .map(|i| (format!("shard{i}.txt"), output(Shard { i, count })))
+            .collect()
+    }
+
+    #[test]
+    fn round_trip() {
+        for s in ["1/1", "2/3", "3/3"] {
+            assert_eq!(Shard::parse(s).unwrap().to_string(), s);
+        }
+        for s in ["0/3", "4/3", "1/0", "1", "a/3", "1/3/5", "-1/3"] {
+            assert!(Shard::parse(s).is_err(), "{s:?}");
+        }
+        let complete = lines()
+            .into_iter()
+            .map(|(_, line)| format!("{line}\n"))
+            .collect::<String>();
+        for count in [1, 2, 3, 8, 11] {
+            let mut outputs = shards(count);
+            assert_eq!(merge(&outputs).unwrap(), complete);
+            // the order of the shards does not matter
+            outputs.reverse();
+            assert_eq!(merge(&outputs).unwrap(), complete);
+        }
+    }
+
+    #[test]
+    fn previous() {
+        let shard = Shard { i: 2, count: 3 };
+        let mut masks = Vec::new();
+        let mut next = shard.previous(8);
+        while let Some(mask) = next {
+            masks.push(mask);
+            next = shard.previous(mask);
+        }
+        assert_eq!(masks, [7, 4, 1]);
+        assert!(masks.iter().all(|mask| shard.contains(*mask)));
+        assert_eq!(Shard::ALL.previous(1), Some(0));
+        assert_eq!(Shard::ALL.previous(0), None);
+    }
+
+    #[test]
+    fn invalid_shards() {
+        let error = |outputs: &[(String, String)]| merge(outputs).unwrap_err();
+        assert_eq!(error(&[]), "no shards given");
+
+        // interrupted shards
+        let mut outputs = shards(3);
+        outputs[1].1 = outputs[1]
+            .1
+            .lines()
+            .take(3)
+            .map(|line| format!("{line}\n"))
+            .collect();
+        assert_eq!(error(&outputs), "shard2.txt: shard 2/3 is incomplete");
+        // even if the last line is cut off
+        let length = outputs[1].1.len() - 2;
+        outputs[1].1.truncate(length);
+        assert_eq!(error(&outputs), "shard2.txt: shard 2/3 is incomplete");
+        outputs[1].1 += "\nu^{";
+        assert!(error(&outputs).starts_with("shard2.txt: invalid line"));
+
+        let mut outputs = shards(3);
+        outputs.remove(1);
+        assert_eq!(error(&outputs), "shard 2/3 is missing");
+
+        let mut outputs = shards(3);
+        outputs.push(outputs[0].clone());
+        assert!(error(&outputs).contains("is also given by"));
+
+        let mut outputs = shards(3);
+        outputs[2].1 = outputs[2].1.replace("order 4", "order 3");
+        assert!(error(&outputs).contains("belongs to a different computation"));
+
+        let mut outputs = shards(3);
+        outputs.push(("other.txt".to_owned(), output(Shard { i: 4, count: 4 })));
+        assert!(error(&outputs).contains("the number of shards differs"));
+
+        // a line of another shard
+        let mut outputs = shards(3);
+        let (_, line) = lines().into_iter().find(|(mask, _)| *mask == 6).unwrap();
+        outputs[1].1 = outputs[1]
+            .1
+            .replace("# shard 2/3 done", &format!("{line}\n# shard 2/3 done"));
+        assert!(error(&outputs).contains("does not belong to shard 2/3"));
+
+        let mut outputs = shards(3);
+        outputs[0].1 = outputs[0].1.lines().skip(1).collect::<Vec<_>>().join("\n");
+        assert!(error(&outputs).contains("not the output of a shard"));
+
+        let mut outputs = shards(3);
+        outputs[0].1 += "u^{0}c_{}^*≡\n";
+        assert!(error(&outputs).contains("output after the end of the shard"));
+    }
+}
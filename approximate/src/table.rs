@@ -0,0 +1,366 @@
+// SPDX-FileCopyrightText: Alois Wohlschlager <wohlschlager@math.lmu.de>
+// SPDX-License-Identifier: EUPL-1.2
+
+//! The formula tables describing the relations in the cohomology ring, as generated by
+//! `generate-exact --table`. See the comment at the top of the generated files for the format.
+
+use crate::Exponent;
+
+/// the term `c*v1^e[0]*…*v4^e[3]*z_{zs}`
+#[derive(Clone)]
+pub struct Term {
+    pub c: i128,
+    pub e: [Exponent; 4],
+    pub zs: Vec<u16>,
+}
+
+// the polynomial (coefficients[0]+coefficients[1]*x+…)/denominator, which is integer-valued
+#[derive(Clone)]
+struct Polynomial {
+    denominator: i128,
+    coefficients: Vec<i128>,
+}
+
+impl Polynomial {
+    fn evaluate(&self, x: i128) -> i128 {
+        let value = self
+            .coefficients
+            .iter()
+            .rev()
+            .fold(0, |accum, c| accum * x + c);
+        assert_eq!(
+            value % self.denominator,
+            0,
+            "polynomial is not integer-valued"
+        );
+        value / self.denominator
+    }
+}
+
+// the families of terms of the relation for z_k^2 with k >= start
+#[derive(Clone)]
+enum General {
+    Single([Exponent; 4], Polynomial),
+    Fixed(u8, [Exponent; 4], Polynomial),
+    Generic(u8, [Exponent; 4], Polynomial),
+}
+
+#[derive(Clone)]
+pub struct Table {
+    pub order: Exponent,
+    /// whether the table describes connective K-theory, where all v's except v1 vanish
+    pub connective: bool,
+    /// the FNV-1a hash of the text the table was parsed from, identifying it in files depending on
+    /// it
+    pub checksum: u64,
+    u: Vec<Term>,
+    // the terms of c_1^*
+    dual: Vec<Term>,
+    squares: Vec<(u8, Term)>,
+    start: u8,
+    general: Vec<General>,
+}
+
+impl Table {
+    pub fn parse(s: &str) -> Result<Table, String> {
+        let mut order = None;
+        let mut connective = false;
+        let mut u = Vec::new();
+        let mut dual = Vec::new();
+        let mut squares = Vec::new();
+        let mut start = None;
+        let mut general = Vec::new();
+        for (i, line) in s.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let error = |e: String| format!("line {}: {e}", i + 1);
+            let mut tokens = line.split_whitespace();
+            let keyword = tokens.next().unwrap();
+            let tokens = tokens
+                .map(|token| {
+                    token
+                        .parse::<i128>()
+                        .map_err(|_| format!("invalid number {token:?}"))
+                })
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(error)?;
+            match (keyword, tokens.as_slice()) {
+                ("order", [o]) => order = Some(small(*o).map_err(error)?),
+                ("connective", []) => connective = true,
+                ("u", [c, e1, e2, e3, e4, zs @ ..]) => u.push(Term {
+                    c: *c,
+                    e: exponents(&[*e1, *e2, *e3, *e4], order).map_err(error)?,
+                    zs: indices(zs).map_err(error)?,
+                }),
+                ("dual", [c, e1, e2, e3, e4, j]) => dual.push(Term {
+                    c: *c,
+                    e: exponents(&[*e1, *e2, *e3, *e4], order).map_err(error)?,
+                    zs: indices(&[j + 1]).map_err(error)?,
+                }),
+                ("square", [k, c, e1, e2, e3, e4, zs @ ..]) => squares.push((
+                    small(*k).map_err(error)?,
+                    Term {
+                        c: *c,
+                        e: exponents(&[*e1, *e2, *e3, *e4], order).map_err(error)?,
+                        zs: indices(zs).map_err(error)?,
+                    },
+                )),
+                ("general", [k0]) => start = Some(small(*k0).map_err(error)?),
+                ("single", [e1, e2, e3, e4, p @ ..]) => general.push(General::Single(
+                    exponents(&[*e1, *e2, *e3, *e4], order).map_err(error)?,
+                    polynomial(p).map_err(error)?,
+                )),
+                ("fixed", [i, e1, e2, e3, e4, p @ ..]) => general.push(General::Fixed(
+                    small(*i).map_err(error)?,
+                    exponents(&[*e1, *e2, *e3, *e4], order).map_err(error)?,
+                    polynomial(p).map_err(error)?,
+                )),
+                ("generic", [i0, e1, e2, e3, e4, p @ ..]) => general.push(General::Generic(
+                    small(*i0).map_err(error)?,
+                    exponents(&[*e1, *e2, *e3, *e4], order).map_err(error)?,
+                    polynomial(p).map_err(error)?,
+                )),
+                _ => return Err(error(format!("invalid entry {line:?}"))),
+            }
+        }
+        Ok(Table {
+            order: order.ok_or("missing truncation order")?,
+            connective,
+            checksum: s.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+                (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+            }),
+            u,
+            dual,
+            squares,
+            start: start.ok_or("missing general relations")?,
+            general,
+        })
+    }
+
+    /// The table for connective K-theory, obtained by dropping all terms involving v2, v3 or v4.
+    /// Its order then bounds the exponent of v1.
+    pub fn to_connective(&self) -> Table {
+        let keep = |e: &[Exponent; 4]| e[1..] == [0; 3];
+        Table {
+            order: self.order,
+            connective: true,
+            checksum: self.checksum,
+            u: self.u.iter().filter(|x| keep(&x.e)).cloned().collect(),
+            dual: self.dual.iter().filter(|x| keep(&x.e)).cloned().collect(),
+            squares: self
+                .squares
+                .iter()
+                .filter(|(_, x)| keep(&x.e))
+                .cloned()
+                .collect(),
+            start: self.start,
+            general: self
+                .general
+                .iter()
+                .filter(|family| match family {
+                    General::Single(e, _) | General::Fixed(_, e, _) | General::Generic(_, e, _) => {
+                        keep(e)
+                    }
+                })
+                .cloned()
+                .collect(),
+        }
+    }
+
+    pub fn u(&self) -> impl Iterator<Item = &Term> {
+        self.u.iter()
+    }
+
+    /// the terms of c_i^*
+    pub fn dual(&self, i: u8) -> impl Iterator<Item = Term> + '_ {
+        self.dual.iter().map(move |term| Term {
+            c: i128::pow(-1, (i + 1).into()) * term.c,
+            e: term.e,
+            zs: vec![u16::from(i) - 1 + term.zs[0]],
+        })
+    }
+
+    /// the terms of z_k^2
+    pub fn square(&self, k: u8) -> Vec<Term> {
+        if k < self.start {
+            return self
+                .squares
+                .iter()
+                .filter(|(k2, _)| *k2 == k)
+                .map(|(_, term)| term.clone())
+                .collect();
+        }
+        let sign = i128::pow(-1, (k + 1).into());
+        let k_ = i128::from(k);
+        let k = u16::from(k);
+        // at most the truncation order, as checked when parsing
+        let w = |e| u16::try_from(weight(e)).unwrap();
+        let mut result = Vec::new();
+        for family in &self.general {
+            match family {
+                General::Single(e, p) => result.push(Term {
+                    c: sign * p.evaluate(k_),
+                    e: *e,
+                    zs: vec![2 * k + w(e)],
+                }),
+                General::Fixed(i, e, p) => {
+                    let i = u16::from(*i);
+                    if 2 * i < 2 * k + w(e) {
+                        result.push(Term {
+                            c: sign * p.evaluate(k_),
+                            e: *e,
+                            zs: vec![i, 2 * k + w(e) - i],
+                        });
+                    }
+                }
+                General::Generic(i0, e, p) => {
+                    let w = w(e);
+                    for i in (u16::from(*i0)..).take_while(|i| 2 * i < 2 * k + w) {
+                        result.push(Term {
+                            c: sign * i128::pow(-1, i.into()) * p.evaluate(k_ - i128::from(i)),
+                            e: *e,
+                            zs: vec![i, 2 * k + w - i],
+                        });
+                    }
+                }
+            }
+        }
+        result
+    }
+}
+
+/// the degree of `v1^e[0]*…*v4^e[3]`
+pub fn weight(e: &[Exponent; 4]) -> u32 {
+    [1, 3, 7, 15]
+        .iter()
+        .zip(e)
+        .map(|(w, e)| w * u32::from(*e))
+        .sum()
+}
+
+fn small(x: i128) -> Result<u8, String> {
+    u8::try_from(x).map_err(|_| format!("number {x} out of range"))
+}
+
+// the exponents of a monomial, whose degree may not exceed the truncation order given before
+fn exponents(e: &[i128], order: Option<Exponent>) -> Result<[Exponent; 4], String> {
+    let order = order.ok_or("monomial before the truncation order")?;
+    let e = [small(e[0])?, small(e[1])?, small(e[2])?, small(e[3])?];
+    if weight(&e) > order.into() {
+        return Err(format!(
+            "monomial of degree {} above the truncation order",
+            weight(&e),
+        ));
+    }
+    Ok(e)
+}
+
+fn indices(zs: &[i128]) -> Result<Vec<u16>, String> {
+    zs.iter()
+        .map(|i| u16::try_from(*i).map_err(|_| format!("index {i} out of range")))
+        .collect()
+}
+
+fn polynomial(p: &[i128]) -> Result<Polynomial, String> {
+    match p {
+        [denominator, coefficients @ ..] if *denominator > 0 => Ok(Polynomial {
+            denominator: *denominator,
+            coefficients: coefficients.to_vec(),
+        }),
+        _ => Err("invalid polynomial".to_owned()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DEFAULT_TABLE;
+
+    // the nonzero terms of degree at most `order` in the v's, in a canonical order, as the
+    // polynomials of the general relations may vanish for some k
+    fn terms(x: impl IntoIterator<Item = Term>, order: Exponent) -> Vec<(i128, [u8; 4], Vec<u16>)> {
+        let mut result = x
+            .into_iter()
+            .filter(|term| term.c != 0 && weight(&term.e) <= order.into())
+            .map(|term| (term.c, term.e, term.zs))
+            .collect::<Vec<_>>();
+        result.sort();
+        result
+    }
+
+    #[test]
+    fn default_table() {
+        let table = Table::parse(DEFAULT_TABLE).unwrap();
+        assert_eq!(table.order, 4);
+        // z_1^2 = z_2 + v1*z_3 + …
+        let square = table
+            .square(1)
+            .into_iter()
+            .map(|term| (term.c, term.e, term.zs))
+            .collect::<Vec<_>>();
+        assert!(square.contains(&(1, [0; 4], vec![2])));
+        assert!(square.contains(&(1, [1, 0, 0, 0], vec![3])));
+    }
+
+    #[test]
+    fn generated_tables() {
+        // the tables generated for lower orders agree with the default one up to their order
+        let default = Table::parse(DEFAULT_TABLE).unwrap();
+        for (order, ck) in [(2, false), (3, true)] {
+            let s = generate_exact::table::generate(order.into(), 4 * u32::from(order) + 12, ck);
+            let table = Table::parse(&s).unwrap();
+            let default = if ck {
+                default.to_connective()
+            } else {
+                default.clone()
+            };
+            assert_eq!(table.order, order);
+            assert_eq!(table.connective, ck);
+            assert_eq!(
+                terms(table.u().cloned(), order),
+                terms(default.u().cloned(), order),
+            );
+            for k in 1..=16 {
+                assert_eq!(terms(table.dual(k), order), terms(default.dual(k), order));
+                assert_eq!(
+                    terms(table.square(k), order),
+                    terms(default.square(k), order),
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn checksum() {
+        assert_eq!(
+            Table::parse("order 0\ngeneral 1\n").unwrap().checksum,
+            0x8f21_0e39_2487_0800,
+        );
+        let table = Table::parse(DEFAULT_TABLE).unwrap();
+        assert_eq!(table.to_connective().checksum, table.checksum);
+        // even comments change the checksum
+        let other = Table::parse(&format!("{DEFAULT_TABLE}# comment\n")).unwrap();
+        assert_ne!(other.checksum, table.checksum);
+    }
+
+    #[test]
+    fn invalid_tables() {
+        let (head, _) = DEFAULT_TABLE.split_once("general").unwrap();
+        for s in [
+            head,
+            "general 1\n",
+            "order 4\ngeneral 1\nsingle 0 0 0 0 0 1\n",
+            "order 4\ngeneral 1\nu 1 0 0\n",
+            "order 4\ngeneral 1\nu 1 0 0 0 x 1\n",
+            "order 4\ngeneral 1\nsquare 300 1 0 0 0 0 1\n",
+            "order 4\ngeneral 1\nzero 1\n",
+            "order 4\ngeneral 1\nu 1 0 2 0 0 1\n",
+            "order 255\ngeneral 1\nu 1 0 0 0 255 1\n",
+            "general 1\nu 1 0 0 0 0 1\norder 4\n",
+        ] {
+            assert!(Table::parse(s).is_err(), "{s:?}");
+        }
+    }
+}
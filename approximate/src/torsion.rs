@@ -0,0 +1,126 @@
+// SPDX-FileCopyrightText: Alois Wohlschlager <wohlschlager@math.lmu.de>
+// SPDX-License-Identifier: EUPL-1.2
+
+//! Smith normal forms over Z/2^t. Since Z/2^t is a local principal ideal ring, every entry of
+//! minimal 2-adic valuation can be used as a pivot, and all elementary divisors are powers of 2.
+
+use generate_exact::integer::inverse;
+use std::collections::BTreeMap;
+
+pub struct Pivot {
+    pub column: usize,
+    /// the elementary divisor is 2^exponent
+    pub exponent: u8,
+    /// the pivot row, normalized to have the entry 2^exponent in the pivot column; all its entries
+    /// are divisible by 2^exponent, and the ones in the columns of the previous pivots vanish
+    pub row: Vec<u64>,
+    /// the pivot row as a linear combination of the original rows
+    pub combination: Vec<(usize, u64)>,
+}
+
+// the summands of the linear combinations during the elimination
+enum Atom {
+    Row(usize),
+    Pivot(usize),
+}
+
+/// The pivots of the elimination of the matrix with the given rows, whose entries must be reduced
+/// modulo 2^t. Their exponents are the nonzero elementary divisors.
+pub fn smith_normal_form(rows: Vec<Vec<u64>>, t: u8) -> Vec<Pivot> {
+    let mask = (1 << t) - 1;
+    let mut rows = rows
+        .into_iter()
+        .enumerate()
+        .map(|(i, row)| (row, vec![(Atom::Row(i), 1_u64)]))
+        .collect::<Vec<_>>();
+    let mut pivots = Vec::<Pivot>::new();
+    rows.retain(|(row, _)| row.iter().any(|x| *x != 0));
+    loop {
+        // an entry of minimal valuation, stopping early at units
+        let mut pivot = None;
+        'search: for (i, (row, _)) in rows.iter().enumerate() {
+            for (j, x) in row.iter().enumerate() {
+                if *x != 0 && pivot.is_none_or(|(_, _, e)| x.trailing_zeros() < e) {
+                    pivot = Some((i, j, x.trailing_zeros()));
+                    if x.trailing_zeros() == 0 {
+                        break 'search;
+                    }
+                }
+            }
+        }
+        let Some((i, j, e)) = pivot else {
+            break;
+        };
+
+        // normalize the pivot to 2^e, then clear its column; since all entries of the pivot row
+        // are divisible by 2^e, clearing the row by column operations only affects the pivot row,
+        // which can then be dropped
+        let (mut row, mut combination) = rows.swap_remove(i);
+        let inverse = inverse(row[j] >> e);
+        for x in &mut row {
+            *x = x.wrapping_mul(inverse) & mask;
+        }
+        for (_, c) in &mut combination {
+            *c = c.wrapping_mul(inverse) & mask;
+        }
+        for (other, other_combination) in &mut rows {
+            let factor = other[j] >> e;
+            if factor != 0 {
+                for (x, y) in other.iter_mut().zip(&row) {
+                    *x = x.wrapping_sub(factor.wrapping_mul(*y)) & mask;
+                }
+                other_combination.push((Atom::Pivot(pivots.len()), factor.wrapping_neg() & mask));
+            }
+        }
+        rows.retain(|(row, _)| row.iter().any(|x| *x != 0));
+
+        // expand the linear combination in terms of the original rows
+        let mut expanded = BTreeMap::new();
+        for (atom, c) in combination {
+            let summands = match atom {
+                Atom::Row(i) => vec![(i, c)],
+                Atom::Pivot(k) => pivots[k]
+                    .combination
+                    .iter()
+                    .map(|(i, c2)| (*i, c.wrapping_mul(*c2)))
+                    .collect(),
+            };
+            for (i, c) in summands {
+                let entry = expanded.entry(i).or_insert(0_u64);
+                *entry = entry.wrapping_add(c) & mask;
+            }
+        }
+        expanded.retain(|_, c| *c != 0);
+        pivots.push(Pivot {
+            column: j,
+            exponent: u8::try_from(e).unwrap(),
+            row,
+            combination: expanded.into_iter().collect(),
+        });
+    }
+    pivots
+}
+
+/// A linear form which is 1 on the pivot row of `pivots[k]` divided by 2^e, where e is its
+/// exponent, and divisible by 2^e on all rows of the matrix. It shows that the quotient by the row
+/// space contains an element of order 2^e.
+pub fn witness(pivots: &[Pivot], k: usize, t: u8) -> Vec<u64> {
+    let mask = (1 << t) - 1;
+    // the coefficients of the previous pivot columns, making the form vanish on the previous
+    // pivot rows, which are triangular in these columns; on all other rows, it is divisible by
+    // 2^e since the pivot has minimal valuation
+    let mut mu = vec![0_u64; k];
+    for i in (0..k).rev() {
+        let row = &pivots[i].row;
+        let s = (i + 1..k).fold(row[pivots[k].column], |accum, j| {
+            accum.wrapping_add(mu[j].wrapping_mul(row[pivots[j].column])) & mask
+        });
+        mu[i] = (s.wrapping_neg() & mask) >> pivots[i].exponent;
+    }
+    let mut result = vec![0; pivots[k].row.len()];
+    result[pivots[k].column] = 1;
+    for (pivot, mu) in pivots.iter().zip(mu) {
+        result[pivot.column] = mu;
+    }
+    result
+}
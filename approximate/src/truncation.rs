@@ -0,0 +1,166 @@
+// SPDX-FileCopyrightText: Alois Wohlschlager <wohlschlager@math.lmu.de>
+// SPDX-License-Identifier: EUPL-1.2
+
+//! The monomial ideals in the v's modulo which the computations are performed. Besides all
+//! monomials of degree larger than the truncation order, they may contain the multiples of further
+//! monomials, such as v2^2, so that for example v1 is kept to high order while v2 is truncated
+//! early.
+
+use crate::{table, BasisElement, Exponent};
+use std::fmt::{Display, Formatter};
+
+/// the ideal generated by the monomials of degree larger than `order` and the `generators`, where
+/// `[e1, e2, e3, e4]` stands for v1^e1*…*v4^e4
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Truncation {
+    pub order: Exponent,
+    pub generators: Vec<[Exponent; 4]>,
+}
+
+impl Truncation {
+    /// the ideal of all monomials of degree larger than `order`
+    pub fn order(order: Exponent) -> Self {
+        Truncation {
+            order,
+            generators: Vec::new(),
+        }
+    }
+
+    /// The ideal generated by the monomials of degree larger than `order` and those given in the
+    /// form `v1^6,v2^2` or `(v1^2*v2, v3)`.
+    pub fn parse(order: Exponent, generators: &str) -> Result<Self, String> {
+        let s = generators.trim();
+        let s = s
+            .strip_prefix('(')
+            .and_then(|s| s.strip_suffix(')'))
+            .unwrap_or(s);
+        let generators = s
+            .split(',')
+            .map(|generator| {
+                let mut e: [Exponent; 4] = [0; 4];
+                for factor in generator.split('*').map(str::trim) {
+                    let invalid = || format!("invalid monomial {:?}", generator.trim());
+                    let (v, exponent) = factor.split_once('^').unwrap_or((factor, "1"));
+                    let i = v
+                        .strip_prefix('v')
+                        .and_then(|i| i.parse::<usize>().ok())
+                        .filter(|i| (1..=4).contains(i))
+                        .ok_or_else(invalid)?;
+                    e[i - 1] = exponent
+                        .parse::<Exponent>()
+                        .ok()
+                        .and_then(|exponent| e[i - 1].checked_add(exponent))
+                        .ok_or_else(invalid)?;
+                }
+                if e == [0; 4] {
+                    return Err("the ideal must not contain 1".to_owned());
+                }
+                Ok(e)
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        Ok(Truncation {
+            order,
+            // the generators of larger degree are contained in the ideal anyway
+            generators: generators
+                .into_iter()
+                .filter(|e| table::weight(e) <= order.into())
+                .collect(),
+        })
+    }
+
+    /// whether the monomial v1^e1*…*v4^e4 lies in the ideal
+    pub fn contains(&self, e: &[Exponent; 4]) -> bool {
+        table::weight(e) > self.order.into()
+            || self
+                .generators
+                .iter()
+                .any(|g| g.iter().zip(e).all(|(gi, ei)| gi <= ei))
+    }
+
+    /// whether the v-part of the basis element lies in the ideal, so that it is dropped
+    pub fn drops<M>(&self, m: &BasisElement<M>) -> bool {
+        self.contains(&[m.v1, m.v2, m.v3, m.v4])
+    }
+
+    /// The largest N such that the ideal contains no monomial of degree at most N. In the degrees
+    /// of at least d-N, the truncation loses no information, just as for the truncation order N.
+    pub fn exact_order(&self) -> Exponent {
+        let order = self
+            .generators
+            .iter()
+            .map(|g| table::weight(g).saturating_sub(1))
+            .fold(self.order.into(), u32::min);
+        Exponent::try_from(order).unwrap()
+    }
+}
+
+// the generators besides the monomials of large degree, in the form accepted by `parse`
+impl Display for Truncation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let generators = self
+            .generators
+            .iter()
+            .map(|g| {
+                let factors = g
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, e)| **e != 0)
+                    .map(|(i, e)| match e {
+                        1 => format!("v{}", i + 1),
+                        e => format!("v{}^{e}", i + 1),
+                    })
+                    .collect::<Vec<_>>();
+                factors.join("*")
+            })
+            .collect::<Vec<_>>();
+        write!(f, "({})", generators.join(","))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let truncation = Truncation::parse(8, "v1^3*v2, v2^2,v1*v1*v3^0").unwrap();
+        assert_eq!(
+            truncation.generators,
+            [[3, 1, 0, 0], [0, 2, 0, 0], [2, 0, 0, 0]]
+        );
+        assert_eq!(truncation.to_string(), "(v1^3*v2,v2^2,v1^2)");
+        assert_eq!(
+            Truncation::parse(8, &truncation.to_string()).unwrap(),
+            truncation,
+        );
+    }
+
+    #[test]
+    fn ideal() {
+        // v1^4*v2 has degree 7 and is dropped from the generators
+        let truncation = Truncation::parse(6, "(v2^2, v1^4*v2)").unwrap();
+        assert_eq!(truncation.generators, [[0, 2, 0, 0]]);
+        assert!(truncation.contains(&[1, 2, 0, 0]));
+        assert!(truncation.contains(&[7, 0, 0, 0]));
+        assert!(!truncation.contains(&[3, 1, 0, 0]));
+        assert_eq!(truncation.exact_order(), 5);
+        assert_eq!(Truncation::order(6).exact_order(), 6);
+    }
+
+    #[test]
+    fn invalid() {
+        for s in [
+            "",
+            "v1,",
+            "v5",
+            "v0^2",
+            "w1",
+            "v1^x",
+            "v1^-1",
+            "v1^200*v1^100",
+            "v2^0",
+        ] {
+            assert!(Truncation::parse(4, s).is_err(), "{s:?}");
+        }
+    }
+}
@@ -0,0 +1,12 @@
+// SPDX-FileCopyrightText: Alois Wohlschlager <wohlschlager@math.lmu.de>
+// SPDX-License-Identifier: EUPL-1.2
+
+mod common;
+
+#[test]
+fn local_agrees_with_integer() {
+    assert_eq!(
+        common::run(&["--coefficients", "local", "5"]),
+        common::run(&["--coefficients", "integer", "5"]),
+    );
+}
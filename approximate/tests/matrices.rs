@@ -0,0 +1,12 @@
+// SPDX-FileCopyrightText: Alois Wohlschlager <wohlschlager@math.lmu.de>
+// SPDX-License-Identifier: EUPL-1.2
+
+mod common;
+
+#[test]
+fn matrices_agree_with_default_engine() {
+    for args in [&["6"][..], &["--torsion", "6"], &["--ck", "6"]] {
+        let matrices = [&["--matrices"][..], args].concat();
+        assert_eq!(common::run(&matrices), common::run(args), "{args:?}");
+    }
+}
@@ -0,0 +1,23 @@
+// SPDX-FileCopyrightText: Alois Wohlschlager <wohlschlager@math.lmu.de>
+// SPDX-License-Identifier: EUPL-1.2
+
+mod common;
+
+use std::{fs::File, path::Path};
+
+// the rational elements computed natively with exact coefficients are those of `approximate`
+#[test]
+fn native_agrees_with_approximate() {
+    for n in 2..=5 {
+        let path = Path::new(env!("CARGO_TARGET_TMPDIR")).join(format!("native-{n}"));
+        generate_exact::native::run(n, None, false, &mut File::create(&path).unwrap());
+        let output = common::run(&["--compare", path.to_str().unwrap(), &n.to_string()]);
+        let summary = output.lines().last().unwrap();
+        let (mismatches, compared) = summary
+            .strip_suffix(" rational elements differ from the exact ones")
+            .and_then(|counts| counts.split_once(" of "))
+            .unwrap_or_else(|| panic!("n = {n}: {output}"));
+        assert_eq!(mismatches, "0", "n = {n}: {output}");
+        assert_ne!(compared, "0", "n = {n}");
+    }
+}
@@ -0,0 +1,28 @@
+// SPDX-FileCopyrightText: Alois Wohlschlager <wohlschlager@math.lmu.de>
+// SPDX-License-Identifier: EUPL-1.2
+
+mod common;
+
+// the verdict at the end of the torsion report
+fn torsion(args: &[&str]) -> String {
+    let stdout = common::run(args);
+    stdout.lines().last().unwrap().to_owned()
+}
+
+#[test]
+fn no_torsion_for_small_n() {
+    assert_eq!(torsion(&["--torsion", "4"]), "no torsion found");
+    assert_eq!(torsion(&["--torsion", "5"]), "no torsion found");
+}
+
+#[test]
+fn torsion_for_n_6() {
+    assert_eq!(torsion(&["--torsion", "6"]), "torsion in degree 17: Z/2^1");
+}
+
+#[test]
+fn chow_pre_screening() {
+    assert_eq!(torsion(&["--chow", "--torsion", "5"]), "no torsion found");
+    let stdout = common::run(&["--chow", "--torsion", "6"]);
+    assert!(stdout.contains("candidate degree 21: 1 of 1 classes irrational modulo 2"));
+}
@@ -0,0 +1,402 @@
+// SPDX-FileCopyrightText: Alois Wohlschlager <wohlschlager@math.lmu.de>
+// SPDX-License-Identifier: EUPL-1.2
+
+use std::{
+    cmp::Ordering,
+    fmt::{Display, Formatter},
+    ops::{Add, Mul, Neg, Sub},
+    str::FromStr,
+};
+
+/// Arbitrary precision integers. Values fitting into an i64 are kept inline, since the vast
+/// majority of coefficients occurring in practice are small.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Integer {
+    Small(i64),
+    // sign and little-endian magnitude without leading zero limbs, never fits into an i64
+    Large(bool, Vec<u32>),
+}
+
+impl Integer {
+    pub fn zero() -> Self {
+        Integer::Small(0)
+    }
+
+    pub fn one() -> Self {
+        Integer::Small(1)
+    }
+
+    pub fn is_zero(&self) -> bool {
+        *self == Integer::Small(0)
+    }
+
+    pub fn is_one(&self) -> bool {
+        *self == Integer::Small(1)
+    }
+
+    pub fn is_negative(&self) -> bool {
+        match self {
+            Integer::Small(x) => *x < 0,
+            Integer::Large(negative, _) => *negative,
+        }
+    }
+
+    pub fn abs(&self) -> Self {
+        if self.is_negative() {
+            -self
+        } else {
+            self.clone()
+        }
+    }
+
+    fn from_parts(negative: bool, mut magnitude: Vec<u32>) -> Self {
+        trim(&mut magnitude);
+        let value = match magnitude.len() {
+            0 => Some(0),
+            1 => Some(i128::from(magnitude[0])),
+            2 => Some(i128::from(magnitude[0]) | i128::from(magnitude[1]) << 32),
+            _ => None,
+        }
+        .map(|value| if negative { -value } else { value })
+        .and_then(|value| i64::try_from(value).ok());
+        match value {
+            Some(value) => Integer::Small(value),
+            None => Integer::Large(negative, magnitude),
+        }
+    }
+
+    fn to_parts(&self) -> (bool, Vec<u32>) {
+        match self {
+            Integer::Small(x) => {
+                let magnitude = x.unsigned_abs();
+                let mut result = vec![magnitude as u32, (magnitude >> 32) as u32];
+                trim(&mut result);
+                (*x < 0, result)
+            }
+            Integer::Large(negative, magnitude) => (*negative, magnitude.clone()),
+        }
+    }
+
+    /// truncating division, i.e. the remainder has the sign of the dividend
+    pub fn div_rem(&self, other: &Self) -> (Self, Self) {
+        assert!(!other.is_zero(), "division by zero");
+        if let (Integer::Small(a), Integer::Small(b)) = (self, other) {
+            if let (Some(q), Some(r)) = (a.checked_div(*b), a.checked_rem(*b)) {
+                return (Integer::Small(q), Integer::Small(r));
+            }
+        }
+        let (a_negative, a) = self.to_parts();
+        let (b_negative, b) = other.to_parts();
+        let (q, r) = div_rem_magnitude(&a, &b);
+        (
+            Integer::from_parts(a_negative != b_negative, q),
+            Integer::from_parts(a_negative, r),
+        )
+    }
+
+    pub fn gcd(&self, other: &Self) -> Self {
+        let mut a = self.abs();
+        let mut b = other.abs();
+        while !b.is_zero() {
+            let r = a.div_rem(&b).1;
+            a = b;
+            b = r;
+        }
+        a
+    }
+}
+
+impl From<i64> for Integer {
+    fn from(value: i64) -> Self {
+        Integer::Small(value)
+    }
+}
+
+impl Neg for &Integer {
+    type Output = Integer;
+
+    fn neg(self) -> Integer {
+        match self {
+            Integer::Small(x) => match x.checked_neg() {
+                Some(y) => Integer::Small(y),
+                None => Integer::from_parts(false, vec![0, 1 << 31]),
+            },
+            Integer::Large(negative, magnitude) => {
+                Integer::from_parts(!negative, magnitude.clone())
+            }
+        }
+    }
+}
+
+impl Neg for Integer {
+    type Output = Integer;
+
+    fn neg(self) -> Integer {
+        -&self
+    }
+}
+
+impl Add for &Integer {
+    type Output = Integer;
+
+    fn add(self, other: &Integer) -> Integer {
+        if let (Integer::Small(a), Integer::Small(b)) = (self, other) {
+            if let Some(c) = a.checked_add(*b) {
+                return Integer::Small(c);
+            }
+        }
+        let (a_negative, a) = self.to_parts();
+        let (b_negative, b) = other.to_parts();
+        if a_negative == b_negative {
+            Integer::from_parts(a_negative, add_magnitude(&a, &b))
+        } else {
+            match cmp_magnitude(&a, &b) {
+                Ordering::Less => Integer::from_parts(b_negative, sub_magnitude(&b, &a)),
+                _ => Integer::from_parts(a_negative, sub_magnitude(&a, &b)),
+            }
+        }
+    }
+}
+
+impl Sub for &Integer {
+    type Output = Integer;
+
+    fn sub(self, other: &Integer) -> Integer {
+        if let (Integer::Small(a), Integer::Small(b)) = (self, other) {
+            if let Some(c) = a.checked_sub(*b) {
+                return Integer::Small(c);
+            }
+        }
+        self + &-other
+    }
+}
+
+impl Mul for &Integer {
+    type Output = Integer;
+
+    fn mul(self, other: &Integer) -> Integer {
+        if let (Integer::Small(a), Integer::Small(b)) = (self, other) {
+            if let Some(c) = a.checked_mul(*b) {
+                return Integer::Small(c);
+            }
+        }
+        let (a_negative, a) = self.to_parts();
+        let (b_negative, b) = other.to_parts();
+        Integer::from_parts(a_negative != b_negative, mul_magnitude(&a, &b))
+    }
+}
+
+impl Display for Integer {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Integer::Small(x) => write!(f, "{x}"),
+            Integer::Large(negative, magnitude) => {
+                let mut digits = Vec::new();
+                let mut rest = magnitude.clone();
+                while !rest.is_empty() {
+                    let (q, r) = div_rem_magnitude(&rest, &[1_000_000_000]);
+                    digits.push(r.first().copied().unwrap_or(0));
+                    rest = q;
+                }
+                if *negative {
+                    write!(f, "-")?;
+                }
+                write!(f, "{}", digits.pop().unwrap())?;
+                for chunk in digits.iter().rev() {
+                    write!(f, "{chunk:09}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl FromStr for Integer {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (negative, digits) = match s.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, s),
+        };
+        if digits.is_empty() || !digits.bytes().all(|c| c.is_ascii_digit()) {
+            return Err(format!("invalid integer {s:?}"));
+        }
+        let ten = Integer::from(10);
+        let magnitude = digits.bytes().fold(Integer::zero(), |accum, c| {
+            &(&accum * &ten) + &Integer::from(i64::from(c - b'0'))
+        });
+        Ok(if negative { -magnitude } else { magnitude })
+    }
+}
+
+/// the inverse of an odd number modulo 2^64, by Newton iteration
+pub fn inverse(x: u64) -> u64 {
+    // correct modulo 2^3, and every step doubles the number of correct bits
+    let mut result = x;
+    for _ in 0..5 {
+        result = result.wrapping_mul(2_u64.wrapping_sub(x.wrapping_mul(result)));
+    }
+    result
+}
+
+fn trim(magnitude: &mut Vec<u32>) {
+    while magnitude.last() == Some(&0) {
+        magnitude.pop();
+    }
+}
+
+fn cmp_magnitude(a: &[u32], b: &[u32]) -> Ordering {
+    a.len()
+        .cmp(&b.len())
+        .then_with(|| a.iter().rev().cmp(b.iter().rev()))
+}
+
+fn add_magnitude(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut result = Vec::with_capacity(usize::max(a.len(), b.len()) + 1);
+    let mut carry = 0;
+    for i in 0..usize::max(a.len(), b.len()) {
+        let sum = u64::from(a.get(i).copied().unwrap_or(0))
+            + u64::from(b.get(i).copied().unwrap_or(0))
+            + carry;
+        result.push(sum as u32);
+        carry = sum >> 32;
+    }
+    result.push(carry as u32);
+    trim(&mut result);
+    result
+}
+
+// requires a >= b
+fn sub_magnitude(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut result = Vec::with_capacity(a.len());
+    let mut borrow = 0;
+    for (i, x) in a.iter().enumerate() {
+        let difference = i64::from(*x) - i64::from(b.get(i).copied().unwrap_or(0)) - borrow;
+        result.push(difference as u32);
+        borrow = i64::from(difference < 0);
+    }
+    assert_eq!(borrow, 0);
+    trim(&mut result);
+    result
+}
+
+fn mul_magnitude(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut result = vec![0; a.len() + b.len()];
+    for (i, x) in a.iter().enumerate() {
+        let mut carry = 0;
+        for (j, y) in b.iter().enumerate() {
+            let product = u64::from(*x) * u64::from(*y) + u64::from(result[i + j]) + carry;
+            result[i + j] = product as u32;
+            carry = product >> 32;
+        }
+        result[i + b.len()] = carry as u32;
+    }
+    trim(&mut result);
+    result
+}
+
+// Knuth's algorithm D, see The Art of Computer Programming, volume 2, section 4.3.1
+fn div_rem_magnitude(a: &[u32], b: &[u32]) -> (Vec<u32>, Vec<u32>) {
+    if cmp_magnitude(a, b) == Ordering::Less {
+        return (Vec::new(), a.to_vec());
+    }
+    if b.len() == 1 {
+        let divisor = u64::from(b[0]);
+        let mut quotient = vec![0; a.len()];
+        let mut remainder = 0;
+        for i in (0..a.len()).rev() {
+            let current = remainder << 32 | u64::from(a[i]);
+            quotient[i] = (current / divisor) as u32;
+            remainder = current % divisor;
+        }
+        trim(&mut quotient);
+        let mut remainder = vec![remainder as u32];
+        trim(&mut remainder);
+        return (quotient, remainder);
+    }
+
+    let n = b.len();
+    let shift = b[n - 1].leading_zeros();
+    let b = shift_left(b, shift);
+    let mut u = shift_left(a, shift);
+    u.resize(a.len() + 1, 0);
+    let m = a.len() - n;
+    let mut quotient = vec![0; m + 1];
+    for j in (0..=m).rev() {
+        let numerator = u64::from(u[j + n]) << 32 | u64::from(u[j + n - 1]);
+        let mut q = numerator / u64::from(b[n - 1]);
+        let mut r = numerator % u64::from(b[n - 1]);
+        while q >> 32 != 0 || q * u64::from(b[n - 2]) > (r << 32 | u64::from(u[j + n - 2])) {
+            q -= 1;
+            r += u64::from(b[n - 1]);
+            if r >> 32 != 0 {
+                break;
+            }
+        }
+        let mut borrow = 0;
+        let mut carry = 0;
+        for i in 0..n {
+            let product = q * u64::from(b[i]) + carry;
+            carry = product >> 32;
+            let difference = i64::from(u[i + j]) - borrow - i64::from(product as u32);
+            u[i + j] = difference as u32;
+            borrow = i64::from(difference < 0);
+        }
+        let difference = i64::from(u[j + n]) - borrow - i64::try_from(carry).unwrap();
+        u[j + n] = difference as u32;
+        if difference < 0 {
+            q -= 1;
+            let mut carry = 0;
+            for i in 0..n {
+                let sum = u64::from(u[i + j]) + u64::from(b[i]) + carry;
+                u[i + j] = sum as u32;
+                carry = sum >> 32;
+            }
+            u[j + n] = u[j + n].wrapping_add(carry as u32);
+        }
+        quotient[j] = q as u32;
+    }
+    trim(&mut quotient);
+    u.truncate(n);
+    let mut remainder = shift_right(&u, shift);
+    trim(&mut remainder);
+    (quotient, remainder)
+}
+
+fn shift_left(a: &[u32], shift: u32) -> Vec<u32> {
+    if shift == 0 {
+        return a.to_vec();
+    }
+    let mut result = Vec::with_capacity(a.len() + 1);
+    let mut carry = 0;
+    for x in a {
+        result.push(x << shift | carry);
+        carry = x >> (32 - shift);
+    }
+    if carry != 0 {
+        result.push(carry);
+    }
+    result
+}
+
+fn shift_right(a: &[u32], shift: u32) -> Vec<u32> {
+    if shift == 0 {
+        return a.to_vec();
+    }
+    (0..a.len())
+        .map(|i| a[i] >> shift | a.get(i + 1).map_or(0, |x| x << (32 - shift)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inverses() {
+        for x in [1, 3, 5, 0x0123_4567_89ab_cdef, u64::MAX] {
+            assert_eq!(x.wrapping_mul(inverse(x)), 1, "{x}");
+        }
+    }
+}
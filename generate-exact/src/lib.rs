@@ -0,0 +1,22 @@
+// SPDX-FileCopyrightText: Alois Wohlschlager <wohlschlager@math.lmu.de>
+// SPDX-License-Identifier: EUPL-1.2
+
+//! Exact computations in the Brown-Peterson cohomology of the maximal orthogonal Grassmannian
+//! OGr(n), with rational coefficients in the v's.
+//!
+//! The ring itself is computed natively by [`native::cohomology`], as a quotient of a polynomial
+//! ring ([`ring::QuotientRing`]) in the z(i) with coefficients in Q[v1, v2, …]
+//! ([`polynomial::Polynomial`]). The formal group law is given by the series in
+//! [`formal_group`], and the helpers for monomial symmetric functions in [`symmetric`]. Both are
+//! shared with the generation of the Singular scripts.
+
+pub mod formal_group;
+pub mod integer;
+pub mod native;
+pub mod polynomial;
+pub mod progress;
+pub mod rational;
+pub mod ring;
+pub mod symmetric;
+pub mod table;
+pub mod verify;
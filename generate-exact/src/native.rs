@@ -0,0 +1,244 @@
+// SPDX-FileCopyrightText: Alois Wohlschlager <wohlschlager@math.lmu.de>
+// SPDX-License-Identifier: EUPL-1.2
+
+//! The computations of the Singular scripts, performed natively. The steps mirror the scripts as
+//! closely as possible, so that the results are identical.
+
+use crate::{
+    formal_group::{compose, connective, exponential, logarithm, scale_series},
+    polynomial::Polynomial,
+    rational::Rational,
+    ring::{Element, QuotientRing},
+    symmetric::{neighbour_multiplicity, neighbours, partitions},
+};
+use std::{collections::HashMap, io::Write};
+
+/// The cohomology ring of OGr(n), possibly truncated at some degree in the v's or reduced to
+/// connective K-theory, together with the classes needed for the products of interest.
+pub struct Cohomology {
+    pub n: u32,
+    pub ring: QuotientRing,
+    // cs[i-1] is cs(i), expressed in terms of the z(i)
+    cs: Vec<Element>,
+    pub u: Element,
+}
+
+impl Cohomology {
+    pub fn cs(&self, i: u32) -> Element {
+        match i {
+            0 => self.ring.one(),
+            i if i <= self.n => self.cs[usize::try_from(i - 1).unwrap()].clone(),
+            _ => Element::default(),
+        }
+    }
+
+    pub fn z(&self, i: u32) -> Element {
+        Element::variable(
+            usize::try_from(self.n).unwrap(),
+            usize::try_from(i).unwrap(),
+        )
+    }
+}
+
+/// The cohomology ring, modulo v2, v3, … if `ck` is set, so that only v1 remains. A truncation
+/// then bounds the exponent of v1.
+pub fn cohomology(n: u32, truncation: Option<u32>, ck: bool) -> Cohomology {
+    let d = n * (n + 1) / 2;
+    let n_ = usize::try_from(n).unwrap();
+    // the coefficient of t^i in the series below has degree i-1 in the v's, and the cohomology
+    // ring vanishes above degree d
+    let precision = usize::try_from(u32::min(d, truncation.map_or(d, |t| t + 1)) + 1).unwrap();
+    // the degree bound for the monomials of an element of the given degree
+    let bound = |degree: u32| truncation.map_or(d, |t| u32::min(d, degree + t));
+
+    let (l, e) = if ck {
+        (
+            connective(&logarithm(precision)),
+            connective(&exponential(precision)),
+        )
+    } else {
+        (logarithm(precision), exponential(precision))
+    };
+    // the formal inverse and the 2-series
+    let b = compose(&e, &scale_series(&l, &Rational::from(-1)));
+    let d_ = compose(&e, &scale_series(&l, &Rational::from(2)));
+
+    // Step 1: compute expressions for the Chern classes of the dual
+    let cs = |i: u32| match i {
+        0 => Element::constant(n_, Polynomial::constant(Rational::one())),
+        i if i <= n => Element::variable(n_, usize::try_from(i).unwrap()),
+        _ => Element::default(),
+    };
+    let mut known_monomial_symmetric_functions = HashMap::new();
+    let c = std::iter::once(cs(0))
+        .chain((1..=n).map(|i| {
+            (i..=bound(i))
+                .flat_map(|w| partitions(w, i))
+                .fold(Element::default(), |accum, j| {
+                    let coefficient = j
+                        .iter()
+                        .fold(Polynomial::constant(Rational::one()), |accum, part| {
+                            accum.mul(&b[usize::try_from(*part).unwrap()])
+                        });
+                    let m = monomial_symmetric_function(
+                        n,
+                        d,
+                        &j,
+                        &mut known_monomial_symmetric_functions,
+                    );
+                    accum.add(&m.scale(&coefficient))
+                })
+        }))
+        .collect::<Vec<_>>();
+
+    // Step 2: compute the Chern subalgebra
+    let generators = std::iter::once(cs(n).mul(&cs(n), bound(2 * n)))
+        .chain((1..=n).map(|k| {
+            ((2 * k).saturating_sub(n)..=u32::min(n, 2 * k)).fold(Element::default(), |accum, i| {
+                accum.add(&cs(i).mul(&c[usize::try_from(2 * k - i).unwrap()], bound(2 * k)))
+            })
+        }))
+        .collect::<Vec<_>>();
+    let chern_subalgebra = QuotientRing::new(n_, d, truncation, &generators);
+
+    // Step 3: compute the cohomology ring
+    let z = |i: u32| Element::variable(n_, usize::try_from(i).unwrap());
+    let cs_z = (1..=n)
+        .map(|i| {
+            // the coefficients of the 2-series beyond the precision are truncated anyway
+            (0..=n - i)
+                .take(precision - 1)
+                .fold(Element::default(), |accum, k| {
+                    accum.add(&z(k + i).scale(&d_[usize::try_from(k + 1).unwrap()]))
+                })
+                .scale(&Polynomial::constant(Rational::from(i64::pow(-1, i))))
+        })
+        .collect::<Vec<_>>();
+    let generators = (1..=n)
+        .map(|i| {
+            let square = chern_subalgebra.mul(&cs(i), &cs(i));
+            let cs_i = &cs_z[usize::try_from(i - 1).unwrap()];
+            cs_i.mul(cs_i, bound(2 * i))
+                .sub(&square.substitute(n_, &cs_z, bound(2 * i)))
+        })
+        .collect::<Vec<_>>();
+    let ring = QuotientRing::new(n_, d, truncation, &generators);
+    let mut result = Cohomology {
+        n,
+        ring,
+        cs: cs_z,
+        u: Element::default(),
+    };
+
+    let mut p = vec![Element::default()];
+    for i in 1..precision {
+        let i_ = u32::try_from(i).unwrap();
+        let sign = |j: u32| Polynomial::constant(Rational::from(i64::pow(-1, j)));
+        let pi = (1..i_)
+            .fold(Element::default(), |accum, j| {
+                accum.add(
+                    &result
+                        .ring
+                        .mul(&result.cs(j), &p[usize::try_from(i_ - j).unwrap()])
+                        .scale(&sign(j - 1)),
+                )
+            })
+            .add(
+                &result
+                    .cs(i_)
+                    .scale(&sign(i_ - 1))
+                    .scale(&Polynomial::constant(Rational::from(i64::from(i_)))),
+            );
+        p.push(pi);
+    }
+    let lu = (1..precision)
+        .fold(Element::default(), |accum, i| accum.add(&p[i].scale(&l[i])))
+        .scale(&Polynomial::constant(Rational::new(1.into(), 2.into())));
+    result.u = result.ring.mul(
+        &lu,
+        &(1..precision).rev().fold(Element::default(), |accum, i| {
+            result
+                .ring
+                .mul(&lu, &accum)
+                .add(&Element::constant(n_, e[i].clone()))
+        }),
+    );
+    result
+}
+
+/// Write the products of interest in the format of the last Singular stage.
+pub fn run(n: u32, truncation: Option<u32>, ck: bool, writer: &mut impl Write) {
+    let d = n * (n + 1) / 2;
+    let cohomology = cohomology(n, truncation, ck);
+    let ring = &cohomology.ring;
+
+    // Step 4: compute the products of interest
+    for mask in 0..1 << (n - 1) {
+        let is = (2..=n)
+            .filter(|i| mask & (1 << (i - 2)) != 0)
+            .collect::<Vec<_>>();
+        let mut x = is
+            .iter()
+            .rev()
+            .fold(ring.one(), |accum, i| ring.mul(&cohomology.cs(*i), &accum));
+        for d1 in 0..=d - is.iter().sum::<u32>() {
+            if d1 != 0 {
+                x = ring.mul(&cohomology.u, &x);
+            }
+            writeln!(
+                writer,
+                "{}={}",
+                std::iter::once(format!("u^{d1}"))
+                    .chain(is.iter().map(|i| format!("cs({i})")))
+                    .collect::<Vec<_>>()
+                    .join("*"),
+                x.format("z"),
+            )
+            .unwrap();
+        }
+    }
+}
+
+fn monomial_symmetric_function(
+    n: u32,
+    d: u32,
+    j: &[u32],
+    known: &mut HashMap<Vec<u32>, Element>,
+) -> Element {
+    if let Some(result) = known.get(j) {
+        return result.clone();
+    }
+    let n_ = usize::try_from(n).unwrap();
+    let result = if j.len() > n_ {
+        Element::default()
+    } else if j[0] == 1 {
+        Element::variable(n_, j.len())
+    } else {
+        let jr = j
+            .iter()
+            .filter_map(|part| (*part > 1).then_some(part - 1))
+            .collect::<Vec<_>>();
+        let mjr = monomial_symmetric_function(n, d, &jr, known);
+        let m_neighbours = neighbours(&jr, j.len())
+            .into_iter()
+            .filter(|jn| jn != j)
+            .fold(Element::default(), |accum, jn| {
+                accum.add(&monomial_symmetric_function(n, d, &jn, known).scale(
+                    &Polynomial::constant(Rational::from(
+                        i64::try_from(neighbour_multiplicity(&jn, &jr)).unwrap(),
+                    )),
+                ))
+            });
+        // reduce modulo cs(n)^2
+        Element::variable(n_, j.len())
+            .mul(&mjr, d)
+            .sub(&m_neighbours)
+            .terms()
+            .filter(|(m, _)| m.exponents()[n_ - 1] < 2)
+            .fold(Element::default(), |accum, (m, c)| {
+                accum.add(&Element::term(m.clone(), c.clone()))
+            })
+    };
+    known.insert(j.to_owned(), result.clone());
+    result
+}
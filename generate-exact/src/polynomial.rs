@@ -0,0 +1,293 @@
+// SPDX-FileCopyrightText: Alois Wohlschlager <wohlschlager@math.lmu.de>
+// SPDX-License-Identifier: EUPL-1.2
+
+use crate::rational::Rational;
+use std::{
+    collections::{btree_map::Entry, BTreeMap},
+    fmt::{Display, Formatter},
+    iter::Peekable,
+    str::Chars,
+};
+
+/// Polynomials with rational coefficients. The monomials are exponent vectors without trailing
+/// zeros, so that polynomials in different numbers of variables can be mixed freely. When
+/// displayed, the variables are called v1, v2, … as in the Singular scripts.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Polynomial(BTreeMap<Vec<u8>, Rational>);
+
+impl Polynomial {
+    pub fn constant(c: Rational) -> Self {
+        Polynomial::term(Vec::new(), c)
+    }
+
+    pub fn variable(i: usize) -> Self {
+        let mut exponents = vec![0; i + 1];
+        exponents[i] = 1;
+        Polynomial::term(exponents, Rational::one())
+    }
+
+    fn term(mut exponents: Vec<u8>, c: Rational) -> Self {
+        while exponents.last() == Some(&0) {
+            exponents.pop();
+        }
+        let mut result = Polynomial::default();
+        if !c.is_zero() {
+            result.0.insert(exponents, c);
+        }
+        result
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn terms(&self) -> impl Iterator<Item = (&[u8], &Rational)> {
+        self.0.iter().map(|(m, c)| (m.as_slice(), c))
+    }
+
+    /// the value if the polynomial is constant
+    pub fn as_constant(&self) -> Option<Rational> {
+        match self.0.len() {
+            0 => Some(Rational::zero()),
+            1 => self.0.get(&Vec::new()).cloned(),
+            _ => None,
+        }
+    }
+
+    /// the degree of the first term, where v_i has degree 2^i-1; for homogeneous polynomials, this
+    /// is the degree of the polynomial
+    pub fn degree(&self) -> Option<u32> {
+        self.0.keys().next().map(|m| {
+            m.iter()
+                .enumerate()
+                .map(|(i, e)| ((1 << (i + 1)) - 1) * u32::from(*e))
+                .sum()
+        })
+    }
+
+    fn add_term(&mut self, m: Vec<u8>, c: Rational) {
+        match self.0.entry(m) {
+            Entry::Occupied(mut entry) => {
+                let sum = entry.get() + &c;
+                if sum.is_zero() {
+                    entry.remove();
+                } else {
+                    *entry.get_mut() = sum;
+                }
+            }
+            Entry::Vacant(entry) => {
+                if !c.is_zero() {
+                    entry.insert(c);
+                }
+            }
+        }
+    }
+
+    pub fn add_assign(&mut self, other: &Polynomial) {
+        for (m, c) in &other.0 {
+            self.add_term(m.clone(), c.clone());
+        }
+    }
+
+    /// self += a * b, the workhorse of all ring computations
+    pub fn add_product(&mut self, a: &Polynomial, b: &Polynomial) {
+        for (m1, c1) in &a.0 {
+            for (m2, c2) in &b.0 {
+                let m = (0..usize::max(m1.len(), m2.len()))
+                    .map(|i| m1.get(i).copied().unwrap_or(0) + m2.get(i).copied().unwrap_or(0))
+                    .collect();
+                self.add_term(m, c1 * c2);
+            }
+        }
+    }
+
+    pub fn neg(&self) -> Polynomial {
+        self.scale(&Rational::from(-1))
+    }
+
+    pub fn scale(&self, c: &Rational) -> Polynomial {
+        if c.is_zero() {
+            return Polynomial::default();
+        }
+        Polynomial(self.0.iter().map(|(m, d)| (m.clone(), c * d)).collect())
+    }
+
+    pub fn mul(&self, other: &Polynomial) -> Polynomial {
+        let mut result = Polynomial::default();
+        result.add_product(self, other);
+        result
+    }
+
+    pub fn pow(&self, exponent: u8) -> Polynomial {
+        (0..exponent).fold(Polynomial::constant(Rational::one()), |accum, _| {
+            accum.mul(self)
+        })
+    }
+
+    /// the image under setting v_i to zero for all i > k
+    pub fn restrict(&self, k: usize) -> Polynomial {
+        Polynomial(
+            self.0
+                .iter()
+                .filter(|(m, _)| m.len() <= k)
+                .map(|(m, c)| (m.clone(), c.clone()))
+                .collect(),
+        )
+    }
+}
+
+impl Display for Polynomial {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.0.is_empty() {
+            return write!(f, "0");
+        }
+        for (i, (m, c)) in self.0.iter().rev().enumerate() {
+            let factors = m
+                .iter()
+                .enumerate()
+                .filter(|(_, e)| **e != 0)
+                .map(|(j, e)| match e {
+                    1 => format!("v{}", j + 1),
+                    e => format!("v{}^{e}", j + 1),
+                })
+                .collect::<Vec<_>>();
+            if i != 0 && !c.is_negative() {
+                write!(f, "+")?;
+            }
+            if factors.is_empty() {
+                write!(f, "{c}")?;
+            } else {
+                if *c == Rational::from(-1) {
+                    write!(f, "-")?;
+                } else if !c.is_one() {
+                    write!(f, "{c}*")?;
+                }
+                write!(f, "{}", factors.join("*"))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Parse a polynomial written in Singular syntax. The i-th entry of the exponent vectors of the
+/// result refers to the i-th variable in `variables`. Indexed variable names like `z(1)` are
+/// supported.
+pub fn parse(s: &str, variables: &[&str]) -> Result<Polynomial, String> {
+    let chars = s.chars().filter(|c| !c.is_whitespace()).collect::<String>();
+    let mut parser = Parser {
+        chars: chars.chars().peekable(),
+        variables,
+    };
+    let result = parser.sum()?;
+    match parser.chars.next() {
+        None => Ok(result),
+        Some(c) => Err(format!("unexpected character {c:?} in {s:?}")),
+    }
+}
+
+struct Parser<'a> {
+    chars: Peekable<Chars<'a>>,
+    variables: &'a [&'a str],
+}
+
+impl Parser<'_> {
+    fn sum(&mut self) -> Result<Polynomial, String> {
+        let mut result = Polynomial::default();
+        let mut negative = self.chars.next_if_eq(&'-').is_some();
+        if !negative {
+            self.chars.next_if_eq(&'+');
+        }
+        loop {
+            let summand = self.product()?;
+            result.add_assign(&if negative { summand.neg() } else { summand });
+            negative = match self.chars.peek() {
+                Some('+') => false,
+                Some('-') => true,
+                _ => return Ok(result),
+            };
+            self.chars.next();
+        }
+    }
+
+    fn product(&mut self) -> Result<Polynomial, String> {
+        let mut result = self.power()?;
+        loop {
+            match self.chars.peek() {
+                Some('*') => {
+                    self.chars.next();
+                    result = result.mul(&self.power()?);
+                }
+                Some('/') => {
+                    self.chars.next();
+                    let divisor = self
+                        .power()?
+                        .as_constant()
+                        .ok_or("division by a non-constant")?;
+                    if divisor.is_zero() {
+                        return Err("division by zero".to_owned());
+                    }
+                    result = result.scale(&(&Rational::one() / &divisor));
+                }
+                _ => return Ok(result),
+            }
+        }
+    }
+
+    fn power(&mut self) -> Result<Polynomial, String> {
+        let base = self.atom()?;
+        if self.chars.peek() == Some(&'^') {
+            self.chars.next();
+            let exponent = self.digits()?.parse().map_err(|_| "exponent too large")?;
+            Ok(base.pow(exponent))
+        } else {
+            Ok(base)
+        }
+    }
+
+    fn atom(&mut self) -> Result<Polynomial, String> {
+        match self.chars.peek() {
+            Some('(') => {
+                self.chars.next();
+                let result = self.sum()?;
+                match self.chars.next() {
+                    Some(')') => Ok(result),
+                    c => Err(format!("expected ')', found {c:?}")),
+                }
+            }
+            Some(c) if c.is_ascii_digit() => Ok(Polynomial::constant(self.digits()?.parse()?)),
+            Some(c) if c.is_ascii_alphabetic() => {
+                let mut name = String::new();
+                while let Some(c) = self.chars.peek().filter(|c| c.is_ascii_alphanumeric()) {
+                    name.push(*c);
+                    self.chars.next();
+                }
+                if self.chars.peek() == Some(&'(') {
+                    self.chars.next();
+                    name = format!("{name}({})", self.digits()?);
+                    if self.chars.next() != Some(')') {
+                        return Err(format!("unterminated index in variable {name}"));
+                    }
+                }
+                self.variables
+                    .iter()
+                    .position(|v| *v == name)
+                    .map(Polynomial::variable)
+                    .ok_or_else(|| format!("unknown variable {name}"))
+            }
+            c => Err(format!("unexpected character {c:?}")),
+        }
+    }
+
+    fn digits(&mut self) -> Result<String, String> {
+        let mut result = String::new();
+        while let Some(c) = self.chars.peek().filter(|c| c.is_ascii_digit()) {
+            result.push(*c);
+            self.chars.next();
+        }
+        if result.is_empty() {
+            Err("expected a number".to_owned())
+        } else {
+            Ok(result)
+        }
+    }
+}
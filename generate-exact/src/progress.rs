@@ -0,0 +1,59 @@
+// SPDX-FileCopyrightText: Alois Wohlschlager <wohlschlager@math.lmu.de>
+// SPDX-License-Identifier: EUPL-1.2
+
+//! Estimates of the cost of computing the rational elements, for weighting the progress reports
+//! of long runs. A product of degree k is assumed to cost as much as the number of terms it can
+//! have, that is the number of monomials z_I of degree k up to k plus the truncation order, as
+//! the degree of the z's exceeds the degree of the product by the degree in the v's. Each factor
+//! c_i^* of the product about halves this, as measured for the approximate computations with
+//! n = 9, 10 and 11.
+
+pub struct Costs {
+    // steps[k] is the cost of a multiplication whose result has degree k
+    steps: Vec<f64>,
+}
+
+impl Costs {
+    /// the costs for OGr(n), where the coefficients have degree at most `order` in the v's, if
+    /// given
+    pub fn new(n: u32, order: Option<u32>) -> Self {
+        let d = usize::try_from(n * (n + 1) / 2).unwrap();
+        // the number of subsets of {1, …, n} with sum j, the degree of z_I
+        let mut counts = vec![0.0; d + 1];
+        counts[0] = 1.0;
+        for i in 1..=usize::try_from(n).unwrap() {
+            for j in (i..=d).rev() {
+                counts[j] += counts[j - i];
+            }
+        }
+        let steps = (0..=d)
+            .map(|k| {
+                let end = order.map_or(d, |order| {
+                    usize::min(d, k + usize::try_from(order).unwrap())
+                });
+                counts[k..=end].iter().sum()
+            })
+            .collect();
+        Costs { steps }
+    }
+
+    /// the cost of a multiplication whose result has degree k and contains c factors c_i^*
+    pub fn step(&self, k: u32, c: usize) -> f64 {
+        self.steps[usize::try_from(k).unwrap()] / 2_f64.powi(c.try_into().unwrap())
+    }
+
+    /// the cost of computing u^{d1}c_{cs}^* from scratch, multiplying by the c_i^* in reverse
+    /// order first and then by u
+    pub fn rational_element(&self, d1: u32, cs: &[u32]) -> f64 {
+        let mut degree = 0;
+        let mut result = 0.0;
+        for (j, i) in cs.iter().rev().enumerate() {
+            degree += i;
+            result += self.step(degree, j + 1);
+        }
+        for k in degree + 1..=degree + d1 {
+            result += self.step(k, cs.len());
+        }
+        result
+    }
+}
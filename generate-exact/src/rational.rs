@@ -0,0 +1,158 @@
+// SPDX-FileCopyrightText: Alois Wohlschlager <wohlschlager@math.lmu.de>
+// SPDX-License-Identifier: EUPL-1.2
+
+use crate::integer::Integer;
+use std::{
+    fmt::{Display, Formatter},
+    ops::{Add, Div, Mul, Neg, Sub},
+    str::FromStr,
+};
+
+/// Exact rational numbers, always stored in lowest terms with a positive denominator.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Rational {
+    numerator: Integer,
+    denominator: Integer,
+}
+
+impl Rational {
+    pub fn new(numerator: Integer, denominator: Integer) -> Self {
+        assert!(!denominator.is_zero(), "division by zero");
+        let (numerator, denominator) = if denominator.is_negative() {
+            (-numerator, -denominator)
+        } else {
+            (numerator, denominator)
+        };
+        if denominator.is_one() {
+            return Rational {
+                numerator,
+                denominator,
+            };
+        }
+        let gcd = numerator.gcd(&denominator);
+        Rational {
+            numerator: numerator.div_rem(&gcd).0,
+            denominator: denominator.div_rem(&gcd).0,
+        }
+    }
+
+    pub fn zero() -> Self {
+        Rational::from(0)
+    }
+
+    pub fn one() -> Self {
+        Rational::from(1)
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.numerator.is_zero()
+    }
+
+    pub fn is_one(&self) -> bool {
+        self.numerator.is_one() && self.denominator.is_one()
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.numerator.is_negative()
+    }
+
+    pub fn numerator(&self) -> &Integer {
+        &self.numerator
+    }
+
+    pub fn denominator(&self) -> &Integer {
+        &self.denominator
+    }
+}
+
+impl From<i64> for Rational {
+    fn from(value: i64) -> Self {
+        Rational {
+            numerator: Integer::from(value),
+            denominator: Integer::one(),
+        }
+    }
+}
+
+impl Neg for &Rational {
+    type Output = Rational;
+
+    fn neg(self) -> Rational {
+        Rational {
+            numerator: -&self.numerator,
+            denominator: self.denominator.clone(),
+        }
+    }
+}
+
+impl Add for &Rational {
+    type Output = Rational;
+
+    fn add(self, other: &Rational) -> Rational {
+        if self.denominator == other.denominator {
+            Rational::new(&self.numerator + &other.numerator, self.denominator.clone())
+        } else {
+            Rational::new(
+                &(&self.numerator * &other.denominator) + &(&other.numerator * &self.denominator),
+                &self.denominator * &other.denominator,
+            )
+        }
+    }
+}
+
+impl Sub for &Rational {
+    type Output = Rational;
+
+    fn sub(self, other: &Rational) -> Rational {
+        self + &-other
+    }
+}
+
+impl Mul for &Rational {
+    type Output = Rational;
+
+    fn mul(self, other: &Rational) -> Rational {
+        Rational::new(
+            &self.numerator * &other.numerator,
+            &self.denominator * &other.denominator,
+        )
+    }
+}
+
+impl Div for &Rational {
+    type Output = Rational;
+
+    fn div(self, other: &Rational) -> Rational {
+        Rational::new(
+            &self.numerator * &other.denominator,
+            &self.denominator * &other.numerator,
+        )
+    }
+}
+
+impl Display for Rational {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.denominator.is_one() {
+            write!(f, "{}", self.numerator)
+        } else {
+            write!(f, "{}/{}", self.numerator, self.denominator)
+        }
+    }
+}
+
+impl FromStr for Rational {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once('/') {
+            Some((numerator, denominator)) => {
+                let denominator = denominator.parse::<Integer>()?;
+                if denominator.is_zero() {
+                    return Err(format!("zero denominator in {s:?}"));
+                }
+                Ok(Rational::new(numerator.parse()?, denominator))
+            }
+            None => Ok(Rational::new(s.parse()?, Integer::one())),
+        }
+    }
+}
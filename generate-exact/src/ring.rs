@@ -0,0 +1,353 @@
+// SPDX-FileCopyrightText: Alois Wohlschlager <wohlschlager@math.lmu.de>
+// SPDX-License-Identifier: EUPL-1.2
+
+use crate::{polynomial::Polynomial, rational::Rational};
+use std::{
+    cmp::Ordering,
+    collections::{btree_map::Entry, BTreeMap},
+};
+
+/// A monomial in the variables x_1, …, x_n, where x_i has weight i. Monomials are ordered like
+/// in the Singular ordering ws(1..n), except that the order is reversed: the smallest monomial in
+/// the sense of `Ord` is the leading one. Hence the leading term of an element is always its first
+/// term.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Monomial {
+    degree: u32,
+    exponents: Vec<u8>,
+}
+
+impl Monomial {
+    pub fn one(n: usize) -> Self {
+        Monomial {
+            degree: 0,
+            exponents: vec![0; n],
+        }
+    }
+
+    pub fn variable(n: usize, i: usize) -> Self {
+        let mut result = Monomial::one(n);
+        result.exponents[i - 1] = 1;
+        result.degree = u32::try_from(i).unwrap();
+        result
+    }
+
+    pub fn exponents(&self) -> &[u8] {
+        &self.exponents
+    }
+
+    fn mul(&self, other: &Monomial) -> Monomial {
+        Monomial {
+            degree: self.degree + other.degree,
+            exponents: self
+                .exponents
+                .iter()
+                .zip(&other.exponents)
+                .map(|(e1, e2)| e1 + e2)
+                .collect(),
+        }
+    }
+
+    // the monomial divided by x_i^2
+    fn without_square(&self, i: usize) -> Monomial {
+        let mut result = self.clone();
+        result.exponents[i - 1] -= 2;
+        result.degree -= 2 * u32::try_from(i).unwrap();
+        result
+    }
+}
+
+impl PartialOrd for Monomial {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Monomial {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.degree.cmp(&other.degree).then_with(|| {
+            self.exponents
+                .iter()
+                .rev()
+                .cmp(other.exponents.iter().rev())
+        })
+    }
+}
+
+/// An element of the polynomial ring Q(v1,v2,…)[x_1,…,x_n], or of a quotient of it. Only
+/// polynomial coefficients are ever needed.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Element(BTreeMap<Monomial, Polynomial>);
+
+impl Element {
+    pub fn constant(n: usize, c: Polynomial) -> Self {
+        Element::term(Monomial::one(n), c)
+    }
+
+    pub fn variable(n: usize, i: usize) -> Self {
+        Element::term(
+            Monomial::variable(n, i),
+            Polynomial::constant(Rational::one()),
+        )
+    }
+
+    pub fn term(m: Monomial, c: Polynomial) -> Self {
+        let mut result = Element::default();
+        if !c.is_zero() {
+            result.0.insert(m, c);
+        }
+        result
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// the total degree, where v_i has degree 1-2^i; all elements occurring in the computations
+    /// are homogeneous
+    pub fn degree(&self) -> Option<i64> {
+        self.0
+            .first_key_value()
+            .map(|(m, c)| i64::from(m.degree) - i64::from(c.degree().expect("zero coefficient")))
+    }
+
+    pub fn terms(&self) -> impl Iterator<Item = (&Monomial, &Polynomial)> {
+        self.0.iter()
+    }
+
+    fn add_product_term(&mut self, m: Monomial, c1: &Polynomial, c2: &Polynomial) {
+        match self.0.entry(m) {
+            Entry::Occupied(mut entry) => {
+                entry.get_mut().add_product(c1, c2);
+                if entry.get().is_zero() {
+                    entry.remove();
+                }
+            }
+            Entry::Vacant(entry) => {
+                let c = c1.mul(c2);
+                if !c.is_zero() {
+                    entry.insert(c);
+                }
+            }
+        }
+    }
+
+    pub fn add(&self, other: &Element) -> Element {
+        let one = Polynomial::constant(Rational::one());
+        let mut result = self.clone();
+        for (m, c) in &other.0 {
+            result.add_product_term(m.clone(), c, &one);
+        }
+        result
+    }
+
+    pub fn sub(&self, other: &Element) -> Element {
+        self.add(&other.scale(&Polynomial::constant(Rational::from(-1))))
+    }
+
+    pub fn scale(&self, c: &Polynomial) -> Element {
+        let mut result = Element::default();
+        for (m, d) in &self.0 {
+            result.add_product_term(m.clone(), c, d);
+        }
+        result
+    }
+
+    /// the plain polynomial product, dropping all terms of degree larger than `bound`
+    pub fn mul(&self, other: &Element, bound: u32) -> Element {
+        let mut result = Element::default();
+        for (m1, c1) in &self.0 {
+            for (m2, c2) in &other.0 {
+                if m1.degree + m2.degree <= bound {
+                    result.add_product_term(m1.mul(m2), c1, c2);
+                }
+            }
+        }
+        result
+    }
+
+    /// substitute the i-th variable by images[i-1], which live in n variables
+    pub fn substitute(&self, n: usize, images: &[Element], bound: u32) -> Element {
+        let mut powers = BTreeMap::new();
+        let mut result = Element::default();
+        for (m, c) in &self.0 {
+            let image = substitute_monomial(m, images, bound, n, &mut powers);
+            result = result.add(&image.scale(c));
+        }
+        result
+    }
+
+    /// format in Singular syntax, naming the variables `name(1)`, …, `name(n)`
+    pub fn format(&self, name: &str) -> String {
+        if self.0.is_empty() {
+            return "0".to_owned();
+        }
+        let mut result = String::new();
+        for (m, c) in &self.0 {
+            let factors = m
+                .exponents
+                .iter()
+                .enumerate()
+                .filter(|(_, e)| **e != 0)
+                .map(|(i, e)| match e {
+                    1 => format!("{name}({})", i + 1),
+                    e => format!("{name}({})^{e}", i + 1),
+                })
+                .collect::<Vec<_>>()
+                .join("*");
+            let coefficient = match c.as_constant() {
+                Some(c) if factors.is_empty() => c.to_string(),
+                Some(c) if c.is_one() => String::new(),
+                Some(c) if c == Rational::from(-1) => "-".to_owned(),
+                Some(c) => format!("{c}*"),
+                None if factors.is_empty() => format!("({c})"),
+                None => format!("({c})*"),
+            };
+            if !result.is_empty() && !coefficient.starts_with('-') {
+                result.push('+');
+            }
+            result.push_str(&coefficient);
+            result.push_str(&factors);
+        }
+        result
+    }
+}
+
+fn substitute_monomial(
+    m: &Monomial,
+    images: &[Element],
+    bound: u32,
+    n: usize,
+    known: &mut BTreeMap<Monomial, Element>,
+) -> Element {
+    if let Some(result) = known.get(m) {
+        return result.clone();
+    }
+    let result = match m.exponents.iter().rposition(|e| *e != 0) {
+        None => Element::constant(n, Polynomial::constant(Rational::one())),
+        Some(i) => {
+            let mut rest = m.clone();
+            rest.exponents[i] -= 1;
+            rest.degree -= u32::try_from(i + 1).unwrap();
+            substitute_monomial(&rest, images, bound, n, known).mul(&images[i], bound)
+        }
+    };
+    known.insert(m.clone(), result.clone());
+    result
+}
+
+/// The quotient of Q(v1,v2,…)[x_1,…,x_n] by an ideal with a standard basis whose leading
+/// monomials are exactly the squares x_1^2, …, x_n^2, and by all monomials of degree larger than
+/// `bound`. The square-free monomials form a basis of the quotient, so normal forms are unique.
+/// If a truncation order is given, all terms of degree larger than it in the v's are dropped as
+/// well. Since all elements are homogeneous, this just lowers the degree bound for each element.
+pub struct QuotientRing {
+    n: usize,
+    bound: u32,
+    truncation: Option<u32>,
+    // x_i^2 ≡ squares[i-1] modulo the ideal
+    squares: Vec<Element>,
+}
+
+impl QuotientRing {
+    /// The leading terms of the nonzero generators must be constant multiples of squares of
+    /// variables. If several generators have the same leading monomial, the first one is used for
+    /// rewriting and the others are checked to reduce to zero. The standard basis property then
+    /// follows from Buchberger's product criterion.
+    pub fn new(n: usize, bound: u32, truncation: Option<u32>, generators: &[Element]) -> Self {
+        let mut squares = vec![None; n];
+        let mut redundant = Vec::new();
+        for g in generators {
+            let Some((m, c)) = g.0.first_key_value() else {
+                continue;
+            };
+            let i = m
+                .exponents
+                .iter()
+                .position(|e| *e == 2)
+                .filter(|i| m.degree == 2 * u32::try_from(i + 1).unwrap())
+                .unwrap_or_else(|| panic!("leading monomial of generator is not a square"));
+            let c = c
+                .as_constant()
+                .expect("leading coefficient of generator is not constant");
+            if squares[i].is_none() {
+                let mut tail = g.clone();
+                tail.0.pop_first();
+                squares[i] = Some(tail.scale(&Polynomial::constant(&Rational::from(-1) / &c)));
+            } else {
+                redundant.push(g);
+            }
+        }
+        let result = QuotientRing {
+            n,
+            bound,
+            truncation,
+            squares: squares
+                .into_iter()
+                .enumerate()
+                .map(|(i, square)| {
+                    // squares of too large degree vanish anyway
+                    square.unwrap_or_else(|| {
+                        assert!(2 * u32::try_from(i + 1).unwrap() > bound);
+                        Element::default()
+                    })
+                })
+                .collect(),
+        };
+        for g in redundant {
+            assert!(
+                result.normal_form(g.clone()).is_zero(),
+                "generators do not form a standard basis",
+            );
+        }
+        result
+    }
+
+    pub fn one(&self) -> Element {
+        Element::constant(self.n, Polynomial::constant(Rational::one()))
+    }
+
+    /// the degree bound for the monomials of an element of the given total degree
+    pub fn bound(&self, degree: i64) -> u32 {
+        match self.truncation {
+            Some(truncation) => u32::try_from(degree + i64::from(truncation))
+                .unwrap_or(0)
+                .min(self.bound),
+            None => self.bound,
+        }
+    }
+
+    pub fn normal_form(&self, mut f: Element) -> Element {
+        let Some(degree) = f.degree() else {
+            return f;
+        };
+        let bound = self.bound(degree);
+        let mut result = Element::default();
+        while let Some((m, c)) = f.0.pop_first() {
+            if m.degree > bound {
+                break;
+            }
+            match m.exponents.iter().position(|e| *e >= 2) {
+                None => {
+                    result.0.insert(m, c);
+                }
+                Some(i) => {
+                    let rest = m.without_square(i + 1);
+                    for (m2, c2) in &self.squares[i].0 {
+                        if rest.degree + m2.degree <= bound {
+                            f.add_product_term(rest.mul(m2), &c, c2);
+                        }
+                    }
+                }
+            }
+        }
+        result
+    }
+
+    pub fn mul(&self, a: &Element, b: &Element) -> Element {
+        match (a.degree(), b.degree()) {
+            (Some(d1), Some(d2)) => self.normal_form(a.mul(b, self.bound(d1 + d2))),
+            _ => Element::default(),
+        }
+    }
+}
@@ -0,0 +1,131 @@
+// SPDX-FileCopyrightText: Alois Wohlschlager <wohlschlager@math.lmu.de>
+// SPDX-License-Identifier: EUPL-1.2
+
+//! Monomial symmetric functions m(j) for partitions j, expressed in terms of the elementary
+//! symmetric functions cs(i). They are computed recursively: the product cs(len(j))*m(jr), where jr
+//! is obtained from j by decreasing all parts by one and dropping the zeros, is m(j) plus multiples
+//! of the m(jn) for the other neighbours jn of jr.
+
+use std::{cmp::Reverse, collections::HashSet, io::Write};
+
+/// The partitions of `weight` into exactly `length` parts, with the parts in decreasing order.
+pub fn partitions(weight: u32, length: u32) -> Vec<Vec<u32>> {
+    if length == 1 {
+        vec![vec![weight]]
+    } else {
+        (1..=weight - length + 1)
+            .flat_map(|i| {
+                partitions(weight - i, length - 1)
+                    .into_iter()
+                    .filter_map(move |j| (j[0] <= i).then(|| std::iter::once(i).chain(j).collect()))
+            })
+            .collect()
+    }
+}
+
+/// Write the Singular definition of the monomial symmetric function m(j) in n variables to
+/// `writer`, after those of the ones it depends on, and return its name. The ones already defined
+/// are tracked in `known`.
+pub fn define_monomial_symmetric_function(
+    n: u32,
+    j: &[u32],
+    known: &mut HashSet<Vec<u32>>,
+    writer: &mut dyn Write,
+) -> String {
+    let name = format!(
+        "m({})",
+        j.iter()
+            .map(|part| format!("{part}"))
+            .collect::<Vec<_>>()
+            .join(",")
+    );
+    if !known.contains(j) {
+        if j.len() > n as usize {
+            writeln!(writer, "poly {name} = 0;").unwrap();
+        } else if j[0] == 1 {
+            writeln!(writer, "poly {name} = cs({});", j.len()).unwrap();
+        } else {
+            let jr = j
+                .iter()
+                .filter_map(|part| (*part > 1).then_some(part - 1))
+                .collect::<Vec<_>>();
+            let mjr = define_monomial_symmetric_function(n, &jr, known, writer);
+            let m_neighbours = neighbours(&jr, j.len())
+                .into_iter()
+                .filter(|jn| jn != j)
+                .map(|jn| {
+                    format!(
+                        "{}*{}",
+                        neighbour_multiplicity(&jn, &jr),
+                        define_monomial_symmetric_function(n, &jn, known, writer),
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("+");
+            writeln!(
+                writer,
+                "poly {name} = reduce(cs({})*{mjr}-({m_neighbours}),cs({n})^2);",
+                j.len(),
+            )
+            .unwrap();
+        }
+        known.insert(j.to_owned());
+    }
+    name
+}
+
+/// The partitions obtained from jr by increasing exactly k of its parts by one, where jr is
+/// padded with zeros as needed. These are the jn for which m(jn) occurs in cs(k)*m(jr).
+pub fn neighbours(jr: &[u32], k: usize) -> HashSet<Vec<u32>> {
+    if jr.is_empty() {
+        HashSet::from_iter([vec![1; k]])
+    } else {
+        neighbours(&jr[1..], k - 1)
+            .into_iter()
+            .map(|jn| std::iter::once(jr[0] + 1).chain(jn).collect())
+            .chain(neighbours(&jr[1..], k).into_iter().map(|jn| {
+                let mut result = std::iter::once(jr[0]).chain(jn).collect::<Vec<_>>();
+                result.sort_by_key(|part| Reverse(*part));
+                result
+            }))
+            .collect()
+    }
+}
+
+/// The coefficient of m(jn) in cs(k)*m(jr), for one of the `neighbours(jr, k)`.
+pub fn neighbour_multiplicity(jn: &[u32], jr: &[u32]) -> u64 {
+    do_neighbour_multiplicity(
+        jn,
+        &jr.iter()
+            .copied()
+            .chain(std::iter::repeat_n(0, jn.len() - jr.len()))
+            .collect::<Vec<_>>(),
+    )
+}
+
+fn do_neighbour_multiplicity(jn: &[u32], jr: &[u32]) -> u64 {
+    if jn.is_empty() {
+        1
+    } else {
+        assert!(jn[0] == jr[0] || jn[0] == jr[0] + 1);
+        let lead_n = jn.iter().take_while(|part| **part == jn[0]).count();
+        let lead_r = jr.iter().take_while(|part| **part == jr[0]).count();
+        if jn[0] > jr[0] {
+            assert!(lead_n <= lead_r);
+            neighbour_multiplicity(&jn[lead_n..], &jr[lead_n..])
+        } else {
+            assert!(lead_n >= lead_r);
+            assert!((lead_r..lead_n).all(|i| jn[i] == jr[i] + 1));
+            binom(lead_n as u64, lead_r as u64)
+                * neighbour_multiplicity(&jn[lead_n..], &jr[lead_n..])
+        }
+    }
+}
+
+// computed with 64 bits, since the multiplicities exceed 32 bits for the large n of the tables
+fn binom(n: u64, k: u64) -> u64 {
+    match k {
+        0 => 1,
+        _ => n * binom(n - 1, k - 1) / k,
+    }
+}
@@ -0,0 +1,355 @@
+// SPDX-FileCopyrightText: Alois Wohlschlager <wohlschlager@math.lmu.de>
+// SPDX-License-Identifier: EUPL-1.2
+
+//! Derivation of the formula tables used by the approximate computations. Up to a fixed degree in
+//! the v's, the relations in the cohomology ring of OGr(n) do not depend on n (except that terms
+//! involving z(i) for i > n vanish), and for all but the smallest k, the relation for z(k)^2
+//! follows a uniform pattern whose coefficients are polynomials in k. So the relations for a
+//! single, sufficiently large n determine them for all n, and the polynomials are found by
+//! interpolation. The same works for connective K-theory, where only v1 remains and the
+//! truncation bounds its exponent.
+
+use crate::{
+    integer::Integer, native::cohomology, polynomial::Polynomial, rational::Rational, ring::Element,
+};
+use std::{cmp::Reverse, collections::BTreeMap, fmt::Write};
+
+// the number of data points used only for checking an interpolating polynomial
+const VERIFICATION: usize = 2;
+
+/// the exponents of v1, …, v4 and the indices of the z's
+pub type Term = ([u8; 4], Vec<u32>);
+
+// the polynomials describing the relation for z(k)^2 for all k >= start
+struct General {
+    start: u32,
+    // for each monomial in the v's: the coefficient of the single term, the coefficients of the
+    // products z(i)*z(2k+w-i) with i < generic_start, and the generic coefficient
+    families: Vec<([u8; 4], Family)>,
+}
+
+struct Family {
+    single: Vec<Rational>,
+    fixed: Vec<Vec<Rational>>,
+    generic_start: u32,
+    generic: Vec<Rational>,
+}
+
+/// the formula table up to the truncation order, derived from the relations for OGr(n)
+pub fn generate(truncation: u32, n: u32, ck: bool) -> String {
+    let mut out = String::new();
+    let cohomology = cohomology(n, Some(truncation), ck);
+    let u = terms(&cohomology.u);
+    // c_1^* = -cs(1)
+    let dual = terms(
+        &cohomology
+            .cs(1)
+            .scale(&Polynomial::constant(Rational::from(-1))),
+    );
+    let squares = (1..=n)
+        .map(|k| {
+            let z = cohomology.z(k);
+            terms(&cohomology.ring.mul(&z, &z))
+        })
+        .collect::<Vec<_>>();
+    let general = (1..=n)
+        .find_map(|start| fit_general(&squares, n, truncation, ck, start))
+        .unwrap_or_else(|| panic!("not enough data to determine the relations, increase n"));
+    assert!(
+        2 * (general.start - 1) + truncation <= n,
+        "not enough data to determine the relations, increase n",
+    );
+
+    let title = if ck {
+        format!(
+            "# The relations in the connective K-theory of OGr(n), i.e. modulo v2, v3, …, up to degree
+# {truncation} in v1, valid for all n. Generated by `generate-exact --ck --table {truncation} {n}`."
+        )
+    } else {
+        format!(
+            "# The relations in the cohomology ring of OGr(n) up to degree {truncation} in the v's, valid for all n.
+# Generated by `generate-exact --table {truncation} {n}`."
+        )
+    };
+    writeln!(
+        out,
+        "{title}
+#
+# Terms involving z_i for some i > n are to be dropped. In the following, v^e = v1^e1*…*v4^e4, w
+# is its degree, and P(x) = (c_0+c_1*x+c_2*x^2+…)/q.
+# `u c e1 e2 e3 e4 i…`: u contains c*v^e*z_{{i…}}
+# `dual c e1 e2 e3 e4 j`: c_i^* contains (-1)^(i+1)*c*v^e*z_{{i+j}}
+# `square k c e1 e2 e3 e4 i…`: z_k^2 contains c*v^e*z_{{i…}}
+# `general k0`: the remaining lines describe z_k^2 for all k >= k0
+# `single e1 e2 e3 e4 q c_0 c_1 …`: z_k^2 contains (-1)^(k+1)*P(k)*v^e*z_{{2k+w}}
+# `fixed i e1 e2 e3 e4 q c_0 c_1 …`: z_k^2 contains (-1)^(k+1)*P(k)*v^e*z_{{i,2k+w-i}} if i < 2k+w-i
+# `generic i0 e1 e2 e3 e4 q c_0 c_1 …`: z_k^2 contains (-1)^(k+1+i)*P(k-i)*v^e*z_{{i,2k+w-i}} for
+#   all i >= i0 with i < 2k+w-i{}
+order {truncation}",
+        if ck {
+            "\n# `connective`: only the powers of v1 occur, since all other v's vanish"
+        } else {
+            ""
+        },
+    )
+    .unwrap();
+    if ck {
+        writeln!(out, "connective").unwrap();
+    }
+    for ((e, zs), c) in &u {
+        writeln!(
+            out,
+            "u {} {} {}",
+            integer(c),
+            format_exponents(e),
+            format_indices(zs)
+        )
+        .unwrap();
+    }
+    for ((e, zs), c) in dual.iter().filter(|((e, _), _)| weight(e) <= truncation) {
+        writeln!(
+            out,
+            "dual {} {} {}",
+            integer(c),
+            format_exponents(e),
+            zs[0] - 1
+        )
+        .unwrap();
+    }
+    for k in 1..general.start {
+        for ((e, zs), c) in &squares[usize::try_from(k - 1).unwrap()] {
+            if weight(e) <= truncation {
+                writeln!(
+                    out,
+                    "square {k} {} {} {}",
+                    integer(c),
+                    format_exponents(e),
+                    format_indices(zs),
+                )
+                .unwrap();
+            }
+        }
+    }
+    writeln!(out, "general {}", general.start).unwrap();
+    for (e, family) in &general.families {
+        let e = format_exponents(e);
+        if !family.single.is_empty() {
+            writeln!(out, "single {e} {}", format_polynomial(&family.single)).unwrap();
+        }
+        for (i, p) in family.fixed.iter().enumerate() {
+            if !p.is_empty() {
+                writeln!(out, "fixed {} {e} {}", i + 1, format_polynomial(p)).unwrap();
+            }
+        }
+        if !family.generic.is_empty() {
+            writeln!(
+                out,
+                "generic {} {e} {}",
+                family.generic_start,
+                format_polynomial(&family.generic),
+            )
+            .unwrap();
+        }
+    }
+    out
+}
+
+pub fn terms(x: &Element) -> BTreeMap<Term, Rational> {
+    let mut result = BTreeMap::new();
+    for (m, c) in x.terms() {
+        let zs = m
+            .exponents()
+            .iter()
+            .enumerate()
+            .filter(|(_, e)| **e != 0)
+            .map(|(i, e)| {
+                assert_eq!(*e, 1);
+                u32::try_from(i + 1).unwrap()
+            })
+            .collect::<Vec<_>>();
+        for (v, c) in c.terms() {
+            let mut e = [0; 4];
+            e[..v.len()].copy_from_slice(v);
+            result.insert((e, zs.clone()), c.clone());
+        }
+    }
+    result
+}
+
+/// the degree of v1^e1*…*v4^e4
+pub fn weight(e: &[u8; 4]) -> u32 {
+    e.iter()
+        .enumerate()
+        .map(|(i, e)| ((1 << (i + 1)) - 1) * u32::from(*e))
+        .sum()
+}
+
+/// all monomials in the v's of degree at most `truncation`, by increasing degree
+pub fn v_monomials(truncation: u32) -> Vec<[u8; 4]> {
+    let mut result = vec![[0; 4]];
+    for i in 0..4 {
+        result = result
+            .into_iter()
+            .flat_map(|e| {
+                (0..)
+                    .map(move |ei| {
+                        let mut e = e;
+                        e[i] = ei;
+                        e
+                    })
+                    .take_while(|e| weight(e) <= truncation)
+            })
+            .collect();
+    }
+    result.sort_by_key(|e| (weight(e), Reverse(*e)));
+    result
+}
+
+fn fit_general(
+    squares: &[BTreeMap<Term, Rational>],
+    n: u32,
+    truncation: u32,
+    ck: bool,
+    start: u32,
+) -> Option<General> {
+    let mut families = Vec::new();
+    // modulo v2, v3, …, only the powers of v1 remain
+    for e in v_monomials(truncation)
+        .into_iter()
+        .filter(|e| !ck || e[1..] == [0; 3])
+    {
+        let w = weight(&e);
+        // the largest k for which all terms of z(k)^2 involving v^e are known
+        let end = n.checked_sub(w)? / 2;
+        if end < start {
+            return None;
+        }
+        let coefficient = |k: u32, zs: Vec<u32>| {
+            let sign = Rational::from(i64::pow(-1, k + 1));
+            squares[usize::try_from(k - 1).unwrap()]
+                .get(&(e, zs))
+                .map_or_else(Rational::zero, |c| &sign * c)
+        };
+        for k in start..=end {
+            assert!(
+                squares[usize::try_from(k - 1).unwrap()]
+                    .keys()
+                    .all(|(e2, zs)| *e2 != e || zs.len() <= 2),
+                "relations of unexpected shape",
+            );
+        }
+
+        let single = fit(&(start..=end)
+            .map(|k| (i64::from(k), coefficient(k, vec![2 * k + w])))
+            .collect::<Vec<_>>())?;
+        // the products z(i)*z(2k+w-i) with i >= generic_start follow a common pattern
+        let (generic_start, generic) = (1..).find_map(|generic_start| {
+            let mut points = BTreeMap::new();
+            for k in start..=end {
+                for i in (generic_start..).take_while(|i| 2 * i < 2 * k + w) {
+                    let c =
+                        &Rational::from(i64::pow(-1, i)) * &coefficient(k, vec![i, 2 * k + w - i]);
+                    let x = i64::from(k) - i64::from(i);
+                    if points.insert(x, c.clone()).is_some_and(|c2| c2 != c) {
+                        return None;
+                    }
+                }
+            }
+            if points.len() < VERIFICATION {
+                // give up
+                return Some(None);
+            }
+            fit(&points.into_iter().collect::<Vec<_>>()).map(|p| Some((generic_start, p)))
+        })??;
+        let fixed = (1..generic_start)
+            .map(|i| {
+                fit(&(start..=end)
+                    .filter(|k| 2 * i < 2 * k + w)
+                    .map(|k| (i64::from(k), coefficient(k, vec![i, 2 * k + w - i])))
+                    .collect::<Vec<_>>())
+            })
+            .collect::<Option<Vec<_>>>()?;
+        families.push((
+            e,
+            Family {
+                single,
+                fixed,
+                generic_start,
+                generic,
+            },
+        ));
+    }
+    Some(General { start, families })
+}
+
+// the coefficients of the polynomial of lowest degree through the given points, as long as it is
+// determined by all but the last `VERIFICATION` points
+fn fit(points: &[(i64, Rational)]) -> Option<Vec<Rational>> {
+    (1..=points.len().checked_sub(VERIFICATION)?)
+        .map(|m| interpolate(&points[..m]))
+        .find(|p| points.iter().all(|(x, y)| evaluate(p, *x) == *y))
+}
+
+// the coefficients of the polynomial of degree less than points.len() through the given points
+fn interpolate(points: &[(i64, Rational)]) -> Vec<Rational> {
+    // Newton's divided differences
+    let mut differences = points.iter().map(|(_, y)| y.clone()).collect::<Vec<_>>();
+    for j in 1..points.len() {
+        for l in (j..points.len()).rev() {
+            differences[l] = &(&differences[l] - &differences[l - 1])
+                / &Rational::from(points[l].0 - points[l - j].0);
+        }
+    }
+    let mut result = Vec::new();
+    for (l, difference) in differences.iter().enumerate().rev() {
+        // result = result*(x-x_l)+difference
+        let mut next = vec![Rational::zero(); result.len() + 1];
+        for (j, c) in result.iter().enumerate() {
+            next[j + 1] = &next[j + 1] + c;
+            next[j] = &next[j] - &(c * &Rational::from(points[l].0));
+        }
+        next[0] = &next[0] + difference;
+        result = next;
+    }
+    while result.last().is_some_and(Rational::is_zero) {
+        result.pop();
+    }
+    result
+}
+
+fn evaluate(p: &[Rational], x: i64) -> Rational {
+    p.iter().rev().fold(Rational::zero(), |accum, c| {
+        &(&accum * &Rational::from(x)) + c
+    })
+}
+
+fn integer(c: &Rational) -> &Integer {
+    assert!(c.denominator().is_one(), "non-integral coefficient");
+    c.numerator()
+}
+
+fn format_exponents(e: &[u8; 4]) -> String {
+    e.map(|e| e.to_string()).join(" ")
+}
+
+fn format_indices(zs: &[u32]) -> String {
+    zs.iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// the common denominator, followed by the numerators
+fn format_polynomial(p: &[Rational]) -> String {
+    let denominator = p.iter().fold(Integer::one(), |accum, c| {
+        let gcd = accum.gcd(c.denominator());
+        &accum * &c.denominator().div_rem(&gcd).0
+    });
+    std::iter::once(denominator.to_string())
+        .chain(p.iter().map(|c| {
+            let numerator = c * &Rational::new(denominator.clone(), Integer::one());
+            numerator.numerator().to_string()
+        }))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
@@ -0,0 +1,304 @@
+// SPDX-FileCopyrightText: Alois Wohlschlager <wohlschlager@math.lmu.de>
+// SPDX-License-Identifier: EUPL-1.2
+
+//! Verification of the certificates for torsion written by `approximate --certificate`. All
+//! products are recomputed in the natively computed cohomology ring with exact coefficients, which
+//! shares no code with the approximate computations, and only reduced modulo 2^t afterwards.
+
+use crate::{
+    integer::{inverse, Integer},
+    native::cohomology,
+    polynomial::Polynomial,
+    rational::Rational,
+    table::{terms, v_monomials, weight, Term},
+};
+use std::collections::BTreeMap;
+
+// the claim that the class of y has order 2^exponent in the quotient by the rational elements
+struct Certificate {
+    degree: u32,
+    exponent: u32,
+    // the summands a*v^e*u^{d1}*c_{is}^* of 2^exponent*y, as (a, e, d1, is)
+    combination: Vec<(u64, [u8; 4], u32, Vec<u32>)>,
+    class: BTreeMap<Term, u64>,
+    witness: BTreeMap<Term, u64>,
+}
+
+struct Certificates {
+    n: u32,
+    t: u32,
+    order: u32,
+    // whether the certificates live in connective K-theory, modulo v2, v3, …
+    ck: bool,
+    certificates: Vec<Certificate>,
+}
+
+pub fn run(path: &str) {
+    let s = std::fs::read_to_string(path).unwrap_or_else(|e| panic!("cannot read {path}: {e}"));
+    verify(&s);
+}
+
+// panics unless all certificates in s are valid
+fn verify(s: &str) {
+    let Certificates {
+        n,
+        t,
+        order,
+        ck,
+        certificates,
+    } = parse(s).unwrap_or_else(|e| panic!("invalid certificate: {e}"));
+    assert!((1..=255).contains(&n));
+    assert!(t < 63);
+    let d = n * (n + 1) / 2;
+    assert!(u32::min(d, order + 1) <= 31);
+    let modulus_mask = (1 << t) - 1;
+    for (i, certificate) in certificates.iter().enumerate() {
+        let Certificate {
+            degree, exponent, ..
+        } = *certificate;
+        assert!(
+            0 < exponent && exponent < t,
+            "certificate {}: no torsion claimed",
+            i + 1,
+        );
+        // the truncation does not lose any information in this degree
+        assert!(
+            degree + order >= d && degree <= d,
+            "certificate {}: degree out of range",
+            i + 1,
+        );
+        for (e, zs) in certificate.class.keys().chain(certificate.witness.keys()) {
+            assert_eq!(
+                zs.iter().sum::<u32>(),
+                degree + weight(e),
+                "certificate {}: terms of wrong degree",
+                i + 1,
+            );
+        }
+        assert_eq!(
+            evaluate(&certificate.witness, &certificate.class, [0; 4], t) % 2,
+            1,
+            "certificate {}: the linear form is not a unit on the class",
+            i + 1,
+        );
+    }
+    let Some(lowest) = certificates.iter().map(|c| c.degree).min() else {
+        println!("no certificates");
+        return;
+    };
+
+    let cohomology = cohomology(n, Some(order), ck);
+    let ring = &cohomology.ring;
+    let mut sums = vec![BTreeMap::new(); certificates.len()];
+    for mask in 0..1 << (n - 1) {
+        let is = (2..=n)
+            .filter(|i| mask & (1 << (i - 2)) != 0)
+            .collect::<Vec<_>>();
+        // c_i^* = -cs(i)
+        let mut x = is.iter().rev().fold(ring.one(), |accum, i| {
+            ring.mul(
+                &cohomology
+                    .cs(*i)
+                    .scale(&Polynomial::constant(Rational::from(-1))),
+                &accum,
+            )
+        });
+        for d1 in 0..=d - is.iter().sum::<u32>() {
+            if d1 != 0 {
+                x = ring.mul(&cohomology.u, &x);
+            }
+            let degree = d1 + is.iter().sum::<u32>();
+            if degree < lowest {
+                continue;
+            }
+            let x = terms(&x)
+                .into_iter()
+                .map(|(term, c)| (term, reduce(&c, t)))
+                .filter(|(_, c)| *c != 0)
+                .collect::<BTreeMap<_, _>>();
+            for (i, (certificate, sum)) in certificates.iter().zip(&mut sums).enumerate() {
+                if degree < certificate.degree {
+                    continue;
+                }
+                for e in v_monomials(degree - certificate.degree)
+                    .into_iter()
+                    .filter(|e| weight(e) == degree - certificate.degree)
+                    .filter(|e| !ck || e[1..] == [0; 3])
+                {
+                    assert_eq!(
+                        evaluate(&certificate.witness, &x, e, t) % (1 << certificate.exponent),
+                        0,
+                        "certificate {}: the linear form is not divisible by 2^{} on the rational \
+                         element v^{e:?}*u^{d1}*c_{is:?}^*",
+                        i + 1,
+                        certificate.exponent,
+                    );
+                }
+                for (a, e, _, _) in certificate
+                    .combination
+                    .iter()
+                    .filter(|(_, _, d1_, is_)| *d1_ == d1 && *is_ == is)
+                {
+                    for ((f, zs), c) in &x {
+                        let entry = sum.entry((add(e, f), zs.clone())).or_insert(0_u64);
+                        *entry = entry.wrapping_add(a.wrapping_mul(*c)) & modulus_mask;
+                    }
+                }
+            }
+        }
+    }
+    for (i, (certificate, mut sum)) in certificates.iter().zip(sums).enumerate() {
+        sum.retain(|_, c| *c != 0);
+        let expected = certificate
+            .class
+            .iter()
+            .map(|(term, c)| (term.clone(), (c << certificate.exponent) & modulus_mask))
+            .filter(|(_, c)| *c != 0)
+            .collect::<BTreeMap<_, _>>();
+        assert!(
+            sum == expected,
+            "certificate {}: the linear combination is not 2^{} times the class",
+            i + 1,
+            certificate.exponent,
+        );
+        println!(
+            "certificate {}: element of order 2^{} in degree {} verified",
+            i + 1,
+            certificate.exponent,
+            certificate.degree,
+        );
+    }
+}
+
+fn parse(s: &str) -> Result<Certificates, String> {
+    let mut n = None;
+    let mut t = None;
+    let mut order = None;
+    let mut ck = false;
+    let mut certificates = Vec::<Certificate>::new();
+    for (i, line) in s.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let error = |e: &str| format!("line {}: {e}", i + 1);
+        let mut tokens = line.split_whitespace();
+        let keyword = tokens.next().unwrap();
+        let tokens = tokens
+            .map(|token| token.parse::<u64>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| error("invalid number"))?;
+        let small = |x: u64| u32::try_from(x).map_err(|_| error("number out of range"));
+        let exponents = |e: &[u64]| {
+            let mut result = [0; 4];
+            for (r, e) in result.iter_mut().zip(e) {
+                *r = u8::try_from(*e).map_err(|_| error("number out of range"))?;
+            }
+            Ok::<_, String>(result)
+        };
+        let indices = |zs: &[u64]| zs.iter().map(|i| small(*i)).collect::<Result<Vec<_>, _>>();
+        match (keyword, tokens.as_slice()) {
+            ("n", [x]) => n = Some(small(*x)?),
+            ("t", [x]) => t = Some(small(*x)?),
+            ("order", [x]) => order = Some(small(*x)?),
+            ("connective", []) => ck = true,
+            ("torsion", [degree, exponent]) => certificates.push(Certificate {
+                degree: small(*degree)?,
+                exponent: small(*exponent)?,
+                combination: Vec::new(),
+                class: BTreeMap::new(),
+                witness: BTreeMap::new(),
+            }),
+            (keyword, [c, e1, e2, e3, e4, rest @ ..]) => {
+                let certificate = certificates
+                    .last_mut()
+                    .ok_or_else(|| error("entry outside of a certificate"))?;
+                let e = exponents(&[*e1, *e2, *e3, *e4])?;
+                match (keyword, rest) {
+                    ("combination", [d1, is @ ..]) => {
+                        certificate
+                            .combination
+                            .push((*c, e, small(*d1)?, indices(is)?));
+                    }
+                    ("class", zs) => {
+                        certificate.class.insert((e, indices(zs)?), *c);
+                    }
+                    ("witness", zs) => {
+                        certificate.witness.insert((e, indices(zs)?), *c);
+                    }
+                    _ => return Err(error(&format!("invalid entry {line:?}"))),
+                }
+            }
+            _ => return Err(error(&format!("invalid entry {line:?}"))),
+        }
+    }
+    Ok(Certificates {
+        n: n.ok_or("missing n")?,
+        t: t.ok_or("missing t")?,
+        order: order.ok_or("missing truncation order")?,
+        ck,
+        certificates,
+    })
+}
+
+// the value of the linear form on v^e*x, modulo 2^t
+fn evaluate(form: &BTreeMap<Term, u64>, x: &BTreeMap<Term, u64>, e: [u8; 4], t: u32) -> u64 {
+    x.iter().fold(0, |accum, ((f, zs), c)| {
+        let value = form.get(&(add(&e, f), zs.clone())).copied().unwrap_or(0);
+        accum.wrapping_add(value.wrapping_mul(*c)) & ((1 << t) - 1)
+    })
+}
+
+fn add(e: &[u8; 4], f: &[u8; 4]) -> [u8; 4] {
+    [e[0] + f[0], e[1] + f[1], e[2] + f[2], e[3] + f[3]]
+}
+
+// the residue modulo 2^t of a rational number with odd denominator
+fn reduce(c: &Rational, t: u32) -> u64 {
+    let modulus = Integer::from(1 << t);
+    let residue = |x: &Integer| match x.div_rem(&modulus).1 {
+        Integer::Small(r) => u64::try_from(r.rem_euclid(1 << t)).unwrap(),
+        Integer::Large(..) => unreachable!(),
+    };
+    let denominator = residue(c.denominator());
+    assert_eq!(denominator % 2, 1, "coefficient with even denominator");
+    residue(c.numerator()).wrapping_mul(inverse(denominator)) & ((1 << t) - 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // written by `approximate --torsion --certificate FILE 6`, without the explanatory comments
+    const CERTIFICATE: &str = "\
+n 6
+t 2
+order 4
+torsion 17 1
+combination 1 0 0 0 0 7 4 6
+class 1 1 0 0 0 1 2 4 5 6
+witness 2 2 0 0 0 1 3 4 5 6
+witness 1 1 0 0 0 1 2 4 5 6
+";
+
+    #[test]
+    fn valid_certificate() {
+        verify(CERTIFICATE);
+    }
+
+    #[test]
+    #[should_panic(expected = "certificate 1: the linear combination is not 2^1 times the class")]
+    fn wrong_combination() {
+        verify(&CERTIFICATE.replace("combination 1 ", "combination 2 "));
+    }
+
+    #[test]
+    #[should_panic(expected = "certificate 1: the linear form is not divisible by 2^2")]
+    fn wrong_exponent() {
+        verify(
+            &CERTIFICATE
+                .replace("t 2\n", "t 3\n")
+                .replace("torsion 17 1", "torsion 17 2"),
+        );
+    }
+}